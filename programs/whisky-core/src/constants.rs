@@ -1,5 +1,3 @@
-use anchor_lang::prelude::*;
-
 /// Basis points per 100% (10,000 BPS = 100%)
 pub const BPS_PER_WHOLE: u64 = 10_000;
 
@@ -9,6 +7,9 @@ pub const MAX_BET_OUTCOMES: usize = 256;
 /// Minimum number of bet outcomes required
 pub const MIN_BET_OUTCOMES: usize = 2;
 
+/// Version stamped on every emitted event, bumped on any layout change
+pub const EVENT_VERSION: u8 = 1;
+
 /// PDA Seeds for account derivation
 pub const WHISKY_STATE_SEED: &[u8] = b"WHISKY_STATE";
 pub const GAME_SEED: &[u8] = b"GAME";
@@ -19,7 +20,12 @@ pub const POOL_JACKPOT_SEED: &[u8] = b"POOL_JACKPOT";
 pub const POOL_BONUS_UNDERLYING_TA_SEED: &[u8] = b"POOL_BONUS_UNDERLYING_TA";
 pub const POOL_BONUS_MINT_SEED: &[u8] = b"POOL_BONUS_MINT";
 pub const POOL_LP_MINT_SEED: &[u8] = b"POOL_LP_MINT";
+pub const METADATA_SEED: &[u8] = b"metadata";
 pub const ESCROW_SEED: &[u8] = b"ESCROW";
+pub const WITHDRAW_REQUEST_SEED: &[u8] = b"WITHDRAW_REQUEST";
+pub const APPROVED_MINT_SEED: &[u8] = b"APPROVED_MINT";
+pub const FEE_UNWRAP_SEED: &[u8] = b"FEE_UNWRAP";
+pub const WSOL_TEMP_SEED: &[u8] = b"WSOL_TEMP";
 
 /// Default protocol configuration values
 pub const DEFAULT_ANTI_SPAM_FEE: u64 = 100_000; // 0.0001 SOL
@@ -33,13 +39,50 @@ pub const DEFAULT_JACKPOT_PAYOUT_TO_WHISKY_BPS: u64 = 1_000; // 10%
 pub const DEFAULT_BONUS_TO_JACKPOT_RATIO_BPS: u64 = 1_000; // 10%
 pub const DEFAULT_MAX_HOUSE_EDGE_BPS: u64 = 300; // 3%
 pub const DEFAULT_MAX_CREATOR_FEE_BPS: u64 = 500; // 5%
+pub const DEFAULT_MAX_JACKPOT_FEE_BPS: u64 = 100; // 1%
+pub const DEFAULT_MAX_TOTAL_FEE_BPS: u64 = 1_000; // 10%
 pub const DEFAULT_MAX_PAYOUT_BPS: u64 = 10_000; // 100%
 pub const DEFAULT_POOL_WITHDRAW_FEE_BPS: u64 = 100; // 1%
+pub const DEFAULT_RNG_TIMEOUT_SECONDS: i64 = 600; // 10 minutes
+
+/// Longest withdrawal delay a pool can set, so LPs can always exit
+pub const MAX_WITHDRAW_DELAY_SECONDS: i64 = 2_592_000; // 30 days
+
+/// Bounds on a pool's withdrawal limit, so a limit can't lock LPs out
+pub const MIN_WITHDRAW_LIMIT_BPS: u64 = 100; // 1% per window
+pub const MAX_WITHDRAW_LIMIT_WINDOW_SECONDS: i64 = 604_800; // 7 days
+
+/// Time after the RNG timeout before an unrefunded game's anti-spam fee
+/// can be forfeited to the RNG
+pub const ANTI_SPAM_FORFEIT_DELAY_SECONDS: i64 = 604_800; // 7 days
+
+/// Default minimum wager for new pools
+pub const DEFAULT_POOL_MIN_WAGER: u64 = 1_000_000;
+
+/// LP tokens locked in the pool on the first deposit so the share price
+/// can't be inflated from a near-zero supply
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// LP token metadata
+pub const LP_TOKEN_NAME_PREFIX: &str = "Whisky ";
+pub const LP_TOKEN_NAME_SUFFIX: &str = " LP";
+pub const LP_TOKEN_SYMBOL_SUFFIX: &str = "-LP";
+pub const LP_TOKEN_FALLBACK_SYMBOL_LENGTH: usize = 4; // Mint address prefix used without underlying metadata
 
 /// Game-specific constants
 pub const MAX_MULTIPLIER: u64 = 100_000; // 10x max multiplier in BPS
 pub const MIN_WAGER: u64 = 1_000; // Minimum wager (0.000001 tokens for 6 decimals)
-pub const JACKPOT_BASE_PROBABILITY: u64 = 1_000_000; // 0.0001% base jackpot chance
+
+/// Micro basis points per 100% (1 ubps = 0.0001 bps), the unit of jackpot odds
+pub const UBPS_PER_WHOLE: u64 = 100_000_000;
+
+/// Default jackpot odds
+pub const DEFAULT_JACKPOT_BASE_PROBABILITY_UBPS: u64 = 100; // 0.0001% while the jackpot is empty
+pub const DEFAULT_JACKPOT_MAX_PROBABILITY_UBPS: u64 = 1_000_000; // 1%
+pub const DEFAULT_JACKPOT_EDGE_BPS: u64 = 500; // 5% of the player's jackpot expected value
+
+/// Length of a hex-encoded SHA-256 RNG seed commitment
+pub const SEED_HASH_LENGTH: usize = 64;
 
 /// String length limits
 pub const MAX_STRING_LENGTH: usize = 256;
@@ -49,6 +92,7 @@ pub const MAX_METADATA_LENGTH: usize = 512;
 pub const DISCRIMINATOR_SIZE: usize = 8;
 pub const PUBKEY_SIZE: usize = 32;
 pub const U64_SIZE: usize = 8;
+pub const I64_SIZE: usize = 8;
 pub const U32_SIZE: usize = 4;
 pub const BOOL_SIZE: usize = 1;
 pub const VEC_PREFIX_SIZE: usize = 4; 
\ No newline at end of file
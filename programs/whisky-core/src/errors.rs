@@ -116,6 +116,12 @@ pub enum RngError {
     HashVerificationFailed,
     #[msg("Duplicate settlement")]
     DuplicateSettlement,
+    #[msg("Invalid RNG seed hash format")]
+    InvalidSeedHash,
+    #[msg("RNG seed hash not committed")]
+    SeedHashNotCommitted,
+    #[msg("RNG timeout not reached")]
+    TimeoutNotReached,
 }
 
 #[error_code]
@@ -156,6 +162,10 @@ pub enum GameError {
     InvalidJackpotConfiguration,
     #[msg("Invalid metadata")]
     InvalidMetadata,
+    #[msg("Jackpot fee too high")]
+    JackpotFeeTooHigh,
+    #[msg("Total fees too high")]
+    TotalFeeTooHigh,
 }
 
 #[error_code]
@@ -184,4 +194,14 @@ pub enum PoolError {
     CustomFeeOutOfBounds,
     #[msg("Withdrawal limit exceeded")]
     WithdrawalLimitExceeded,
+    #[msg("Withdrawal request still locked")]
+    WithdrawalLocked,
+    #[msg("Slippage tolerance exceeded")]
+    SlippageExceeded,
+    #[msg("Underlying mint not approved for pool creation")]
+    MintNotApproved,
+    #[msg("Mint has an unsupported token extension")]
+    UnsupportedMintExtension,
+    #[msg("Pool withdrawals must be queued")]
+    WithdrawalDelayRequired,
 } 
\ No newline at end of file
@@ -1,12 +1,15 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::{AssociatedToken},
-    token::{Mint, Token, TokenAccount},
+    metadata::{Metadata, MetadataAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use crate::constants::*;
+use crate::errors::*;
 use crate::state::*;
 
 /// Initialize global Whisky state
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WhiskyInitialize<'info> {
     #[account(
@@ -25,6 +28,7 @@ pub struct WhiskyInitialize<'info> {
 }
 
 /// Set Whisky authority
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WhiskySetAuthority<'info> {
     #[account(
@@ -39,6 +43,7 @@ pub struct WhiskySetAuthority<'info> {
 }
 
 /// Configure Whisky settings
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WhiskySetConfig<'info> {
     #[account(
@@ -52,9 +57,546 @@ pub struct WhiskySetConfig<'info> {
     pub authority: Signer<'info>,
 }
 
+/// Stage an RNG key rotation
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WhiskyStageRngRotation<'info> {
+    #[account(
+        mut,
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0],
+        has_one = authority
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    pub authority: Signer<'info>,
+}
+
+/// Approve a mint for pool creation
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WhiskyApproveMint<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0],
+        has_one = authority
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = ApprovedMint::SPACE,
+        seeds = [APPROVED_MINT_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub approved_mint: Account<'info, ApprovedMint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Revoke a mint's pool creation approval
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WhiskyRevokeMint<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0],
+        has_one = authority
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [APPROVED_MINT_SEED, approved_mint.mint.as_ref()],
+        bump = approved_mint.bump[0]
+    )]
+    pub approved_mint: Account<'info, ApprovedMint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
 /// Initialize a gaming pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolInitialize<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = Pool::SPACE,
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool_authority.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: Pool authority can be any account
+    pub pool_authority: AccountInfo<'info>,
+    
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = underlying_token_mint.decimals,
+        mint::authority = pool,
+        mint::token_program = token_program,
+        seeds = [
+            POOL_LP_MINT_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool_authority.key().as_ref()
+        ],
+        bump
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [POOL_JACKPOT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub pool_jackpot_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Allowlist entry for the underlying mint, required while pool
+    /// creation is restricted
+    #[account(
+        seeds = [APPROVED_MINT_SEED, underlying_token_mint.key().as_ref()],
+        bump = approved_mint.bump[0]
+    )]
+    pub approved_mint: Option<Account<'info, ApprovedMint>>,
+    
+    /// CHECK: Receives the pool creation fee; must match the protocol's distribution recipient
+    #[account(mut, address = whisky_state.distribution_recipient)]
+    pub distribution_recipient: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Create or update LP mint metadata
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolSetLpMetadata<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        seeds = [
+            METADATA_SEED,
+            metadata_program.key().as_ref(),
+            underlying_token_mint.key().as_ref()
+        ],
+        seeds::program = metadata_program.key(),
+        bump
+    )]
+    pub underlying_metadata: Option<Account<'info, MetadataAccount>>,
+    
+    #[account(
+        seeds = [
+            POOL_LP_MINT_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: Created or updated by the metadata program
+    #[account(
+        mut,
+        seeds = [
+            METADATA_SEED,
+            metadata_program.key().as_ref(),
+            lp_mint.key().as_ref()
+        ],
+        seeds::program = metadata_program.key(),
+        bump
+    )]
+    pub lp_metadata: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = authority.key() == pool.pool_authority
+            || authority.key() == whisky_state.authority @ WhiskyError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+    
+    pub metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Update pool configuration
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolUpdateConfig<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_SEED,
+            pool.underlying_token_mint.as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        constraint = authority.key() == pool.pool_authority
+            || authority.key() == whisky_state.authority @ WhiskyError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+}
+
+/// Create a pool's bonus mint and bonus underlying account
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolBonusInitialize<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = underlying_token_mint.decimals,
+        mint::authority = pool,
+        mint::token_program = token_program,
+        seeds = [POOL_BONUS_MINT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = underlying_token_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [POOL_BONUS_UNDERLYING_TA_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = authority.key() == pool.pool_authority
+            || authority.key() == whisky_state.authority @ WhiskyError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Fund a pool's bonus underlying account and mint bonus tokens against it
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolMintBonus<'info> {
+    #[account(
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0],
+        constraint = pool.custom_bonus_token @ WhiskyStateError::FeatureDisabled
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [POOL_BONUS_MINT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [POOL_BONUS_UNDERLYING_TA_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = funder,
+        associated_token::token_program = token_program
+    )]
+    pub funder_underlying_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: Bonus tokens can be minted to any account
+    pub recipient: AccountInfo<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = funder,
+        associated_token::mint = bonus_mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_bonus_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposit tokens into pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolDeposit<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_LP_MINT_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Not needed when paying in or out as native SOL
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Holds the LP tokens locked by the first deposit
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_lp_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Depositor's holding of the whitelist mint, required when the pool
+    /// has `deposit_whitelist_required` set
+    pub whitelist_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Withdraw tokens from pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PoolWithdraw<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_LP_MINT_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Not needed when paying in or out as native SOL
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Queue a withdrawal by escrowing LP tokens
+#[event_cpi]
 #[derive(Accounts)]
-pub struct PoolInitialize<'info> {
+pub struct PoolRequestWithdraw<'info> {
     #[account(
         seeds = [WHISKY_STATE_SEED],
         bump = whisky_state.bump[0]
@@ -62,64 +604,65 @@ pub struct PoolInitialize<'info> {
     pub whisky_state: Account<'info, WhiskyState>,
     
     #[account(
-        init,
-        payer = user,
-        space = Pool::SPACE,
         seeds = [
             POOL_SEED,
             underlying_token_mint.key().as_ref(),
-            pool_authority.key().as_ref()
+            pool.pool_authority.as_ref()
         ],
-        bump
+        bump = pool.bump[0]
     )]
     pub pool: Account<'info, Pool>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
-    
-    /// CHECK: Pool authority can be any account
-    pub pool_authority: AccountInfo<'info>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
-        init,
-        payer = user,
-        mint::decimals = underlying_token_mint.decimals,
-        mint::authority = pool,
         seeds = [
             POOL_LP_MINT_SEED,
             underlying_token_mint.key().as_ref(),
-            pool_authority.key().as_ref()
+            pool.pool_authority.as_ref()
         ],
         bump
     )]
-    pub lp_mint: Account<'info, Mint>,
+    pub lp_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
-        init,
+        init_if_needed,
         payer = user,
-        associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        space = WithdrawRequest::SPACE,
+        seeds = [WITHDRAW_REQUEST_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump
     )]
-    pub pool_underlying_token_account: Account<'info, TokenAccount>,
+    pub withdraw_request: Account<'info, WithdrawRequest>,
     
     #[account(
-        init,
+        init_if_needed,
         payer = user,
-        associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        associated_token::mint = lp_mint,
+        associated_token::authority = withdraw_request,
+        associated_token::token_program = token_program
+    )]
+    pub withdraw_request_lp_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
     )]
-    pub pool_jackpot_token_account: Account<'info, TokenAccount>,
+    pub user_lp_ata: InterfaceAccount<'info, TokenAccount>,
     
     #[account(mut)]
     pub user: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-/// Deposit tokens into pool
+/// Execute a queued withdrawal
+#[event_cpi]
 #[derive(Accounts)]
-pub struct PoolDeposit<'info> {
+pub struct PoolExecuteWithdraw<'info> {
     #[account(
         seeds = [WHISKY_STATE_SEED],
         bump = whisky_state.bump[0]
@@ -127,6 +670,7 @@ pub struct PoolDeposit<'info> {
     pub whisky_state: Account<'info, WhiskyState>,
     
     #[account(
+        mut,
         seeds = [
             POOL_SEED,
             underlying_token_mint.key().as_ref(),
@@ -136,7 +680,7 @@ pub struct PoolDeposit<'info> {
     )]
     pub pool: Account<'info, Pool>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
@@ -147,47 +691,69 @@ pub struct PoolDeposit<'info> {
         ],
         bump
     )]
-    pub lp_mint: Account<'info, Mint>,
+    pub lp_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
     )]
-    pub pool_underlying_token_account: Account<'info, TokenAccount>,
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
-        associated_token::mint = underlying_token_mint,
-        associated_token::authority = user
+        close = user,
+        seeds = [WITHDRAW_REQUEST_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = withdraw_request.bump[0],
+        has_one = user,
+        has_one = pool
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+    
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = withdraw_request,
+        associated_token::token_program = token_program
     )]
-    pub user_underlying_ata: Account<'info, TokenAccount>,
+    pub withdraw_request_lp_ata: InterfaceAccount<'info, TokenAccount>,
     
+    /// Not needed when paying in or out as native SOL
     #[account(
         init_if_needed,
         payer = user,
-        associated_token::mint = lp_mint,
-        associated_token::authority = user
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
     )]
-    pub user_lp_ata: Account<'info, TokenAccount>,
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     #[account(mut)]
     pub user: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-/// Withdraw tokens from pool
+/// Cancel a queued withdrawal and return its LP tokens
+#[event_cpi]
 #[derive(Accounts)]
-pub struct PoolWithdraw<'info> {
-    #[account(
-        seeds = [WHISKY_STATE_SEED],
-        bump = whisky_state.bump[0]
-    )]
-    pub whisky_state: Account<'info, WhiskyState>,
-    
+pub struct PoolCancelWithdraw<'info> {
     #[account(
         seeds = [
             POOL_SEED,
@@ -198,10 +764,9 @@ pub struct PoolWithdraw<'info> {
     )]
     pub pool: Account<'info, Pool>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
-        mut,
         seeds = [
             POOL_LP_MINT_SEED,
             underlying_token_mint.key().as_ref(),
@@ -209,38 +774,45 @@ pub struct PoolWithdraw<'info> {
         ],
         bump
     )]
-    pub lp_mint: Account<'info, Mint>,
+    pub lp_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
-        associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        close = user,
+        seeds = [WITHDRAW_REQUEST_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = withdraw_request.bump[0],
+        has_one = user,
+        has_one = pool
     )]
-    pub pool_underlying_token_account: Account<'info, TokenAccount>,
+    pub withdraw_request: Account<'info, WithdrawRequest>,
     
     #[account(
         mut,
-        associated_token::mint = underlying_token_mint,
-        associated_token::authority = user
+        associated_token::mint = lp_mint,
+        associated_token::authority = withdraw_request,
+        associated_token::token_program = token_program
     )]
-    pub user_underlying_ata: Account<'info, TokenAccount>,
+    pub withdraw_request_lp_ata: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = lp_mint,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
     )]
-    pub user_lp_ata: Account<'info, TokenAccount>,
+    pub user_lp_ata: InterfaceAccount<'info, TokenAccount>,
     
     #[account(mut)]
     pub user: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 /// Initialize player account
+#[event_cpi]
 #[derive(Accounts)]
 pub struct PlayerInitialize<'info> {
     #[account(
@@ -268,6 +840,7 @@ pub struct PlayerInitialize<'info> {
 }
 
 /// Play a game
+#[event_cpi]
 #[derive(Accounts)]
 pub struct PlayGame<'info> {
     #[account(
@@ -277,6 +850,7 @@ pub struct PlayGame<'info> {
     pub whisky_state: Account<'info, WhiskyState>,
     
     #[account(
+        mut,
         seeds = [
             POOL_SEED,
             underlying_token_mint.key().as_ref(),
@@ -302,41 +876,99 @@ pub struct PlayGame<'info> {
     )]
     pub game: Account<'info, Game>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
     )]
-    pub pool_underlying_token_account: Account<'info, TokenAccount>,
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
-        mut,
+        seeds = [POOL_JACKPOT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub pool_jackpot_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Not needed when paying in or out as native SOL
+    #[account(
+        init_if_needed,
+        payer = user,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
     )]
-    pub user_underlying_ata: Account<'info, TokenAccount>,
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = player
+        associated_token::authority = player,
+        associated_token::token_program = token_program
     )]
-    pub player_ata: Account<'info, TokenAccount>,
+    pub player_ata: InterfaceAccount<'info, TokenAccount>,
     
     /// CHECK: Game creator can be any account
     pub creator: AccountInfo<'info>,
     
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = creator,
+        associated_token::token_program = token_program
+    )]
+    pub creator_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = whisky_state,
+        associated_token::token_program = token_program
+    )]
+    pub whisky_state_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Pool bonus mint, required to spend or return bonus tokens
+    #[account(
+        mut,
+        seeds = [POOL_BONUS_MINT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(
+        mut,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_bonus_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
     #[account(mut)]
     pub user: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 /// Claim winnings
+#[event_cpi]
 #[derive(Accounts)]
 pub struct PlayerClaim<'info> {
     #[account(
@@ -353,28 +985,151 @@ pub struct PlayerClaim<'info> {
     )]
     pub game: Account<'info, Game>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = player
+        associated_token::authority = player,
+        associated_token::token_program = token_program
     )]
-    pub player_ata: Account<'info, TokenAccount>,
+    pub player_ata: InterfaceAccount<'info, TokenAccount>,
     
+    /// Not needed when paying in or out as native SOL
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = user,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
     )]
-    pub user_underlying_ata: Account<'info, TokenAccount>,
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
+    #[account(mut)]
     pub user: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Refund an unsettled game
+///
+/// Anyone can refund a timed-out game, which releases the liquidity it
+/// reserved; the escrow always goes back to the player.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GameRefund<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        seeds = [PLAYER_SEED, user.key().as_ref()],
+        bump = player.bump[0],
+        has_one = user
+    )]
+    pub player: Account<'info, Player>,
+    
+    #[account(
+        mut,
+        seeds = [GAME_SEED, user.key().as_ref()],
+        bump = game.bump[0],
+        has_one = user,
+        has_one = pool,
+        constraint = game.token_mint == underlying_token_mint.key() @ WhiskyError::InvalidMint
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(
+        mut,
+        seeds = [
+            POOL_SEED,
+            underlying_token_mint.key().as_ref(),
+            pool.pool_authority.as_ref()
+        ],
+        bump = pool.bump[0]
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = player,
+        associated_token::token_program = token_program
+    )]
+    pub player_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Not needed when paying in or out as native SOL
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_underlying_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Temporary WSOL account that native SOL is wrapped or unwrapped
+    /// through, closed before the instruction returns
+    #[account(
+        init,
+        payer = payer,
+        token::mint = underlying_token_mint,
+        token::authority = user,
+        token::token_program = token_program,
+        seeds = [WSOL_TEMP_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub wsol_temp_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Pool bonus mint, required to spend or return bonus tokens
+    #[account(
+        mut,
+        seeds = [POOL_BONUS_MINT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(
+        mut,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_bonus_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// CHECK: Player who made the game; receives the refund
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+    
+    /// Caller, who pays for the player's token account if it was closed
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 /// Close player account
+#[event_cpi]
 #[derive(Accounts)]
 pub struct PlayerClose<'info> {
     #[account(
@@ -391,7 +1146,8 @@ pub struct PlayerClose<'info> {
         close = user,
         seeds = [GAME_SEED, user.key().as_ref()],
         bump = game.bump[0],
-        has_one = user
+        has_one = user,
+        constraint = game.status != GameStatus::ResultRequested @ PlayerError::GameInProgress
     )]
     pub game: Account<'info, Game>,
     
@@ -399,6 +1155,7 @@ pub struct PlayerClose<'info> {
 }
 
 /// RNG settlement
+#[event_cpi]
 #[derive(Accounts)]
 pub struct RngSettle<'info> {
     #[account(
@@ -410,23 +1167,43 @@ pub struct RngSettle<'info> {
     #[account(
         mut,
         seeds = [GAME_SEED, game.user.as_ref()],
-        bump = game.bump[0]
+        bump = game.bump[0],
+        has_one = pool,
+        constraint = game.token_mint == underlying_token_mint.key() @ WhiskyError::InvalidMint
     )]
     pub game: Account<'info, Game>,
     
     #[account(
+        seeds = [PLAYER_SEED, game.user.as_ref()],
+        bump = player.bump[0]
+    )]
+    pub player: Account<'info, Player>,
+    
+    #[account(
+        mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        associated_token::authority = player,
+        associated_token::token_program = token_program
     )]
-    pub pool_underlying_token_account: Account<'info, TokenAccount>,
+    pub player_ata: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
+        mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = pool
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_underlying_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [POOL_JACKPOT_SEED, pool.key().as_ref()],
+        bump
     )]
-    pub pool_jackpot_token_account: Account<'info, TokenAccount>,
+    pub pool_jackpot_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
+        mut,
         seeds = [
             POOL_SEED,
             underlying_token_mint.key().as_ref(),
@@ -436,12 +1213,48 @@ pub struct RngSettle<'info> {
     )]
     pub pool: Account<'info, Pool>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: Fee recipient recorded on the game
+    #[account(address = game.creator)]
+    pub creator: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = creator,
+        associated_token::token_program = token_program
+    )]
+    pub creator_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = underlying_token_mint,
+        associated_token::authority = whisky_state,
+        associated_token::token_program = token_program
+    )]
+    pub whisky_state_ata: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Underlying backing the pool's bonus tokens, required to settle a
+    /// game that spent bonus tokens
+    #[account(
+        mut,
+        seeds = [POOL_BONUS_UNDERLYING_TA_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_underlying_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// CHECK: Player receiving the anti-spam fee back
+    #[account(mut, address = game.user)]
+    pub user: AccountInfo<'info>,
     
     pub rng: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 /// RNG provide hashed seed
+#[event_cpi]
 #[derive(Accounts)]
 pub struct RngProvideHashedSeed<'info> {
     #[account(
@@ -460,7 +1273,29 @@ pub struct RngProvideHashedSeed<'info> {
     pub rng: Signer<'info>,
 }
 
+/// Forfeit an abandoned game's anti-spam fee to the RNG
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RngForfeitAntiSpamFee<'info> {
+    #[account(
+        seeds = [WHISKY_STATE_SEED],
+        bump = whisky_state.bump[0]
+    )]
+    pub whisky_state: Account<'info, WhiskyState>,
+    
+    #[account(
+        mut,
+        seeds = [GAME_SEED, game.user.as_ref()],
+        bump = game.bump[0]
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(mut)]
+    pub rng: Signer<'info>,
+}
+
 /// Distribute fees
+#[event_cpi]
 #[derive(Accounts)]
 pub struct DistributeFees<'info> {
     #[account(
@@ -470,23 +1305,45 @@ pub struct DistributeFees<'info> {
     )]
     pub whisky_state: Account<'info, WhiskyState>,
     
-    pub underlying_token_mint: Account<'info, Mint>,
+    pub underlying_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = whisky_state
+        associated_token::authority = whisky_state,
+        associated_token::token_program = token_program
     )]
-    pub whisky_state_ata: Account<'info, TokenAccount>,
+    pub whisky_state_ata: InterfaceAccount<'info, TokenAccount>,
     
+    /// Not needed when fees are distributed as native SOL
     #[account(
         mut,
         associated_token::mint = underlying_token_mint,
-        associated_token::authority = whisky_state.distribution_recipient
+        associated_token::authority = whisky_state.distribution_recipient,
+        associated_token::token_program = token_program
     )]
-    pub distribution_recipient_ata: Account<'info, TokenAccount>,
+    pub distribution_recipient_ata: Option<InterfaceAccount<'info, TokenAccount>>,
     
+    /// CHECK: Receives unwrapped SOL fees; must match the protocol's distribution recipient
+    #[account(mut, address = whisky_state.distribution_recipient)]
+    pub distribution_recipient: UncheckedAccount<'info>,
+    
+    /// Temporary WSOL account closed to unwrap native SOL fees, so the fee
+    /// account itself stays open for settlement
+    #[account(
+        init,
+        payer = authority,
+        token::mint = underlying_token_mint,
+        token::authority = whisky_state,
+        token::token_program = token_program,
+        seeds = [FEE_UNWRAP_SEED, whisky_state.key().as_ref()],
+        bump
+    )]
+    pub fee_unwrap_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut)]
     pub authority: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 } 
\ No newline at end of file
@@ -1,11 +1,15 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    metadata::{create_metadata_accounts_v3, CreateMetadataAccountsV3, Metadata},
-    token::{burn, mint_to, transfer, Burn, Mint, MintTo, Token, TokenAccount, Transfer},
+    metadata::{
+        create_metadata_accounts_v3, update_metadata_accounts_v2, CreateMetadataAccountsV3,
+        UpdateMetadataAccountsV2,
+    },
+    token_interface::{
+        burn, close_account, mint_to, transfer_checked, Burn, CloseAccount, MintTo, TransferChecked,
+    },
 };
-use mpl_token_metadata::types::{DataV2, TokenStandard};
-use std::str::FromStr;
+use mpl_token_metadata::types::DataV2;
 
 declare_id!("HBEQTKVQE2BvvVAN6b21srMXcYchHXQFy7kaQmgyoLdb");
 
@@ -21,6 +25,13 @@ use crate::instructions::*;
 use crate::state::*;
 use crate::utils::*;
 
+// The `#[program]` macro still calls the deprecated `AccountInfo::realloc` from
+// the IDL handlers it generates beside the module, so the allow sits on a
+// private module around it rather than on the whole crate.
+#[allow(deprecated)]
+mod processor {
+use super::*;
+
 #[program]
 pub mod whisky_core {
     use super::*;
@@ -43,6 +54,8 @@ pub mod whisky_core {
         whisky_state.bonus_to_jackpot_ratio_bps = DEFAULT_BONUS_TO_JACKPOT_RATIO_BPS;
         whisky_state.max_house_edge_bps = DEFAULT_MAX_HOUSE_EDGE_BPS;
         whisky_state.max_creator_fee_bps = DEFAULT_MAX_CREATOR_FEE_BPS;
+        whisky_state.max_jackpot_fee_bps = DEFAULT_MAX_JACKPOT_FEE_BPS;
+        whisky_state.max_total_fee_bps = DEFAULT_MAX_TOTAL_FEE_BPS;
         whisky_state.max_payout_bps = DEFAULT_MAX_PAYOUT_BPS;
         whisky_state.pool_withdraw_fee_bps = DEFAULT_POOL_WITHDRAW_FEE_BPS;
         whisky_state.pool_creation_allowed = true;
@@ -50,23 +63,43 @@ pub mod whisky_core {
         whisky_state.pool_withdraw_allowed = true;
         whisky_state.playing_allowed = true;
         whisky_state.distribution_recipient = ctx.accounts.initializer.key();
+        whisky_state.rng_timeout_seconds = DEFAULT_RNG_TIMEOUT_SECONDS;
+        whisky_state.pool_creation_restricted = false;
+        whisky_state.jackpot_base_probability_ubps = DEFAULT_JACKPOT_BASE_PROBABILITY_UBPS;
+        whisky_state.jackpot_max_probability_ubps = DEFAULT_JACKPOT_MAX_PROBABILITY_UBPS;
+        whisky_state.jackpot_edge_bps = DEFAULT_JACKPOT_EDGE_BPS;
         whisky_state.bump = [ctx.bumps.whisky_state];
 
+        emit_cpi!(ProtocolInitialized {
+            version: EVENT_VERSION,
+            authority: ctx.accounts.initializer.key(),
+        });
+
         msg!("🥃 Whisky Gaming Protocol initialized!");
         Ok(())
     }
 
     /// Set protocol authority
     pub fn whisky_set_authority(ctx: Context<WhiskySetAuthority>, authority: Pubkey) -> Result<()> {
+        let previous_authority = ctx.accounts.whisky_state.authority;
         ctx.accounts.whisky_state.authority = authority;
+
+        emit_cpi!(AuthorityChanged {
+            version: EVENT_VERSION,
+            previous_authority,
+            new_authority: authority,
+        });
+
         msg!("Authority updated to {}", authority);
         Ok(())
     }
 
     /// Configure protocol settings
+    #[allow(clippy::too_many_arguments)]
     pub fn whisky_set_config(
         ctx: Context<WhiskySetConfig>,
         rng_address: Pubkey,
+        rng_address_2: Pubkey,
         whisky_fee: u64,
         max_creator_fee: u64,
         pool_creation_fee: u64,
@@ -85,10 +118,32 @@ pub mod whisky_core {
         pool_withdraw_allowed: bool,
         playing_allowed: bool,
         distribution_recipient: Pubkey,
+        rng_timeout_seconds: i64,
+        max_jackpot_fee: u64,
+        max_total_fee: u64,
+        pool_creation_restricted: bool,
+        jackpot_base_probability_ubps: u64,
+        jackpot_max_probability_ubps: u64,
+        jackpot_edge_bps: u64,
     ) -> Result<()> {
+        require!(rng_timeout_seconds > 0, WhiskyStateError::InvalidParameter);
+        require!(max_total_fee <= BPS_PER_WHOLE, WhiskyStateError::ConfigurationOutOfBounds);
+        require!(pool_withdraw_fee_bps <= BPS_PER_WHOLE, WhiskyStateError::ConfigurationOutOfBounds);
+        require!(bonus_to_jackpot_ratio_bps <= BPS_PER_WHOLE, WhiskyStateError::ConfigurationOutOfBounds);
+        validate_jackpot_split(&[
+            jackpot_payout_to_user_bps,
+            jackpot_payout_to_creator_bps,
+            jackpot_payout_to_pool_bps,
+            jackpot_payout_to_whisky_bps,
+        ])?;
+        validate_jackpot_odds(jackpot_base_probability_ubps, jackpot_max_probability_ubps, jackpot_edge_bps)?;
+
         let whisky_state = &mut ctx.accounts.whisky_state;
+        apply_rng_rotation(whisky_state, Clock::get()?.unix_timestamp);
+        validate_rng_config_update(whisky_state, &rng_address, &rng_address_2)?;
         
         whisky_state.rng_address = rng_address;
+        whisky_state.rng_address_2 = rng_address_2;
         whisky_state.whisky_fee_bps = whisky_fee;
         whisky_state.max_creator_fee_bps = max_creator_fee;
         whisky_state.pool_creation_fee = pool_creation_fee;
@@ -107,11 +162,109 @@ pub mod whisky_core {
         whisky_state.pool_withdraw_allowed = pool_withdraw_allowed;
         whisky_state.playing_allowed = playing_allowed;
         whisky_state.distribution_recipient = distribution_recipient;
+        whisky_state.rng_timeout_seconds = rng_timeout_seconds;
+        whisky_state.max_jackpot_fee_bps = max_jackpot_fee;
+        whisky_state.max_total_fee_bps = max_total_fee;
+        whisky_state.pool_creation_restricted = pool_creation_restricted;
+        whisky_state.jackpot_base_probability_ubps = jackpot_base_probability_ubps;
+        whisky_state.jackpot_max_probability_ubps = jackpot_max_probability_ubps;
+        whisky_state.jackpot_edge_bps = jackpot_edge_bps;
+
+        emit_cpi!(ProtocolConfigUpdated {
+            version: EVENT_VERSION,
+            authority: ctx.accounts.authority.key(),
+            rng_address,
+            rng_address_2,
+            whisky_fee_bps: whisky_fee,
+            max_creator_fee_bps: max_creator_fee,
+            pool_creation_fee,
+            anti_spam_fee,
+            max_house_edge_bps: max_house_edge,
+            default_pool_fee,
+            jackpot_payout_to_user_bps,
+            jackpot_payout_to_creator_bps,
+            jackpot_payout_to_pool_bps,
+            jackpot_payout_to_whisky_bps,
+            bonus_to_jackpot_ratio_bps,
+            max_payout_bps,
+            pool_withdraw_fee_bps,
+            pool_creation_allowed,
+            pool_deposit_allowed,
+            pool_withdraw_allowed,
+            playing_allowed,
+            distribution_recipient,
+            rng_timeout_seconds,
+            max_jackpot_fee_bps: max_jackpot_fee,
+            max_total_fee_bps: max_total_fee,
+            pool_creation_restricted,
+            jackpot_base_probability_ubps,
+            jackpot_max_probability_ubps,
+            jackpot_edge_bps,
+        });
 
         msg!("Configuration updated");
         Ok(())
     }
 
+    /// Stage a new primary RNG key from `activation_timestamp`
+    pub fn whisky_stage_rng_rotation(
+        ctx: Context<WhiskyStageRngRotation>,
+        rng_address: Pubkey,
+        activation_timestamp: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(rng_address != Pubkey::default(), WhiskyStateError::InvalidParameter);
+        require!(activation_timestamp >= now, WhiskyStateError::InvalidParameter);
+
+        let whisky_state = &mut ctx.accounts.whisky_state;
+        apply_rng_rotation(whisky_state, now);
+        whisky_state.pending_rng_address = rng_address;
+        whisky_state.pending_rng_activation_timestamp = activation_timestamp;
+
+        emit_cpi!(RngRotationScheduled {
+            version: EVENT_VERSION,
+            authority: ctx.accounts.authority.key(),
+            rng_address: whisky_state.rng_address,
+            pending_rng_address: rng_address,
+            activation_timestamp,
+        });
+
+        msg!("🔑 RNG rotation to {} staged for {}", rng_address, activation_timestamp);
+        Ok(())
+    }
+
+    /// Approve an underlying mint for pool creation while creation is restricted
+    pub fn whisky_approve_mint(ctx: Context<WhiskyApproveMint>) -> Result<()> {
+        let approved_mint = &mut ctx.accounts.approved_mint;
+        approved_mint.mint = ctx.accounts.mint.key();
+        approved_mint.bump = [ctx.bumps.approved_mint];
+
+        emit_cpi!(MintApprovalChanged {
+            version: EVENT_VERSION,
+            authority: ctx.accounts.authority.key(),
+            mint: approved_mint.mint,
+            approved: true,
+        });
+
+        msg!("✅ Mint {} approved for pool creation", approved_mint.mint);
+        Ok(())
+    }
+
+    /// Revoke a mint's pool creation approval
+    pub fn whisky_revoke_mint(ctx: Context<WhiskyRevokeMint>) -> Result<()> {
+        let mint = ctx.accounts.approved_mint.mint;
+
+        emit_cpi!(MintApprovalChanged {
+            version: EVENT_VERSION,
+            authority: ctx.accounts.authority.key(),
+            mint,
+            approved: false,
+        });
+
+        msg!("🚫 Mint {} no longer approved for pool creation", mint);
+        Ok(())
+    }
+
     /// Initialize a gaming pool
     pub fn pool_initialize(
         ctx: Context<PoolInitialize>,
@@ -119,7 +272,25 @@ pub mod whisky_core {
         lookup_address: Pubkey,
     ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
-        require!(whisky_state.pool_creation_allowed, WhiskyStateError::PoolCreationNotAllowed);
+        let creator = ctx.accounts.user.key();
+        validate_pool_creation(whisky_state, &creator, ctx.accounts.approved_mint.is_some())?;
+        validate_mint_extensions(&mint_extension_types(
+            &ctx.accounts.underlying_token_mint.to_account_info(),
+        )?)?;
+
+        let creation_fee = calculate_pool_creation_fee(whisky_state, &creator);
+        if creation_fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: ctx.accounts.distribution_recipient.to_account_info(),
+                    },
+                ),
+                creation_fee,
+            )?;
+        }
 
         let pool = &mut ctx.accounts.pool;
         pool.bump = [ctx.bumps.pool];
@@ -127,7 +298,7 @@ pub mod whisky_core {
         pool.pool_authority = pool_authority;
         pool.underlying_token_mint = ctx.accounts.underlying_token_mint.key();
         pool.anti_spam_fee_exempt = false;
-        pool.min_wager = 1_000_000;
+        pool.min_wager = DEFAULT_POOL_MIN_WAGER;
         pool.plays = 0;
         pool.liquidity_checkpoint = 0;
         pool.deposit_limit = false;
@@ -142,34 +313,367 @@ pub mod whisky_core {
         pool.custom_bonus_token = false;
         pool.custom_max_creator_fee = false;
         pool.custom_max_creator_fee_bps = 0;
+        pool.custom_max_jackpot_fee = false;
+        pool.custom_max_jackpot_fee_bps = 0;
+        pool.custom_pool_withdraw_fee = false;
+        pool.custom_pool_withdraw_fee_bps = 0;
+        pool.custom_jackpot_odds = false;
         pool.deposit_whitelist_required = false;
         pool.deposit_whitelist_address = Pubkey::default();
+        pool.withdraw_delay_seconds = 0;
+        validate_pool_config(whisky_state, pool)?;
+
+        emit_cpi!(PoolCreated {
+            version: EVENT_VERSION,
+            pool: pool.key(),
+            pool_authority,
+            token_mint: ctx.accounts.underlying_token_mint.key(),
+            lp_mint: ctx.accounts.lp_mint.key(),
+            lookup_address,
+            creator,
+            creation_fee,
+        });
 
         msg!("🎰 Pool initialized for token {}", ctx.accounts.underlying_token_mint.key());
         Ok(())
     }
 
+    /// Create or update Metaplex metadata for a pool's LP mint
+    pub fn pool_set_lp_metadata(ctx: Context<PoolSetLpMetadata>, uri: String) -> Result<()> {
+        require!(uri.len() <= mpl_token_metadata::MAX_URI_LENGTH, WhiskyStateError::InvalidParameter);
+
+        let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
+        let underlying_symbol = underlying_display_symbol(
+            ctx.accounts.underlying_metadata.as_ref().map(|metadata| metadata.symbol.as_str()),
+            &underlying_token_mint,
+        );
+
+        let data = DataV2 {
+            name: lp_token_name(&underlying_symbol),
+            symbol: lp_token_symbol(&underlying_symbol),
+            uri: uri.clone(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        };
+
+        let name = data.name.clone();
+        let symbol = data.symbol.clone();
+        let pool_seeds = &[
+            POOL_SEED,
+            underlying_token_mint.as_ref(),
+            ctx.accounts.pool.pool_authority.as_ref(),
+            &[ctx.accounts.pool.bump[0]],
+        ];
+
+        if ctx.accounts.lp_metadata.data_is_empty() {
+            create_metadata_accounts_v3(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    CreateMetadataAccountsV3 {
+                        metadata: ctx.accounts.lp_metadata.to_account_info(),
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        mint_authority: ctx.accounts.pool.to_account_info(),
+                        payer: ctx.accounts.authority.to_account_info(),
+                        update_authority: ctx.accounts.pool.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                    &[&pool_seeds[..]],
+                ),
+                data,
+                true,
+                true,
+                None,
+            )?;
+        } else {
+            update_metadata_accounts_v2(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    UpdateMetadataAccountsV2 {
+                        metadata: ctx.accounts.lp_metadata.to_account_info(),
+                        update_authority: ctx.accounts.pool.to_account_info(),
+                    },
+                    &[&pool_seeds[..]],
+                ),
+                None,
+                Some(data),
+                None,
+                None,
+            )?;
+        }
+
+        emit_cpi!(LpMetadataUpdated {
+            version: EVENT_VERSION,
+            pool: ctx.accounts.pool.key(),
+            lp_mint: ctx.accounts.lp_mint.key(),
+            name,
+            symbol,
+            uri,
+        });
+
+        msg!("🏷️ LP metadata set for pool {}", ctx.accounts.pool.key());
+        Ok(())
+    }
+
+    /// Update pool configuration
+    ///
+    /// Signed by the pool authority, or by the protocol authority, which is
+    /// the only one allowed to change `custom_whisky_fee` and
+    /// `anti_spam_fee_exempt`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn pool_update_config(
+        ctx: Context<PoolUpdateConfig>,
+        min_wager: u64,
+        deposit_limit: bool,
+        deposit_limit_amount: u64,
+        custom_pool_fee: bool,
+        custom_pool_fee_bps: u64,
+        custom_max_payout: bool,
+        custom_max_payout_bps: u64,
+        custom_max_creator_fee: bool,
+        custom_max_creator_fee_bps: u64,
+        custom_max_jackpot_fee: bool,
+        custom_max_jackpot_fee_bps: u64,
+        custom_pool_withdraw_fee: bool,
+        custom_pool_withdraw_fee_bps: u64,
+        deposit_whitelist_required: bool,
+        deposit_whitelist_address: Pubkey,
+        custom_whisky_fee: bool,
+        custom_whisky_fee_bps: u64,
+        anti_spam_fee_exempt: bool,
+        withdraw_limit: bool,
+        withdraw_limit_bps: u64,
+        withdraw_limit_window_seconds: i64,
+        withdraw_delay_seconds: i64,
+        custom_jackpot_odds: bool,
+        custom_jackpot_base_probability_ubps: u64,
+        custom_jackpot_max_probability_ubps: u64,
+        custom_jackpot_edge_bps: u64,
+    ) -> Result<()> {
+        let whisky_state = &ctx.accounts.whisky_state;
+        let pool = &mut ctx.accounts.pool;
+        let authority = ctx.accounts.authority.key();
+
+        let protocol_fields_changed = custom_whisky_fee != pool.custom_whisky_fee
+            || custom_whisky_fee_bps != pool.custom_whisky_fee_bps
+            || anti_spam_fee_exempt != pool.anti_spam_fee_exempt;
+        require!(
+            !protocol_fields_changed || authority == whisky_state.authority,
+            WhiskyError::Unauthorized
+        );
+
+        pool.min_wager = min_wager;
+        pool.deposit_limit = deposit_limit;
+        pool.deposit_limit_amount = deposit_limit_amount;
+        pool.custom_pool_fee = custom_pool_fee;
+        pool.custom_pool_fee_bps = custom_pool_fee_bps;
+        pool.custom_max_payout = custom_max_payout;
+        pool.custom_max_payout_bps = custom_max_payout_bps;
+        pool.custom_max_creator_fee = custom_max_creator_fee;
+        pool.custom_max_creator_fee_bps = custom_max_creator_fee_bps;
+        pool.custom_max_jackpot_fee = custom_max_jackpot_fee;
+        pool.custom_max_jackpot_fee_bps = custom_max_jackpot_fee_bps;
+        pool.custom_pool_withdraw_fee = custom_pool_withdraw_fee;
+        pool.custom_pool_withdraw_fee_bps = custom_pool_withdraw_fee_bps;
+        pool.deposit_whitelist_required = deposit_whitelist_required;
+        pool.deposit_whitelist_address = deposit_whitelist_address;
+        pool.custom_whisky_fee = custom_whisky_fee;
+        pool.custom_whisky_fee_bps = custom_whisky_fee_bps;
+        pool.anti_spam_fee_exempt = anti_spam_fee_exempt;
+        pool.withdraw_limit = withdraw_limit;
+        pool.withdraw_limit_bps = withdraw_limit_bps;
+        pool.withdraw_limit_window_seconds = withdraw_limit_window_seconds;
+        pool.withdraw_delay_seconds = withdraw_delay_seconds;
+        pool.custom_jackpot_odds = custom_jackpot_odds;
+        pool.custom_jackpot_base_probability_ubps = custom_jackpot_base_probability_ubps;
+        pool.custom_jackpot_max_probability_ubps = custom_jackpot_max_probability_ubps;
+        pool.custom_jackpot_edge_bps = custom_jackpot_edge_bps;
+
+        validate_pool_config(whisky_state, pool)?;
+
+        emit_cpi!(PoolConfigUpdated {
+            version: EVENT_VERSION,
+            pool: pool.key(),
+            authority,
+            min_wager,
+            deposit_limit,
+            deposit_limit_amount,
+            custom_pool_fee,
+            custom_pool_fee_bps,
+            custom_max_payout,
+            custom_max_payout_bps,
+            custom_max_creator_fee,
+            custom_max_creator_fee_bps,
+            custom_max_jackpot_fee,
+            custom_max_jackpot_fee_bps,
+            custom_pool_withdraw_fee,
+            custom_pool_withdraw_fee_bps,
+            deposit_whitelist_required,
+            deposit_whitelist_address,
+            custom_whisky_fee,
+            custom_whisky_fee_bps,
+            anti_spam_fee_exempt,
+            withdraw_limit,
+            withdraw_limit_bps,
+            withdraw_limit_window_seconds,
+            withdraw_delay_seconds,
+            custom_jackpot_odds,
+            custom_jackpot_base_probability_ubps,
+            custom_jackpot_max_probability_ubps,
+            custom_jackpot_edge_bps,
+        });
+
+        msg!("⚙️ Pool {} configuration updated", pool.key());
+        Ok(())
+    }
+
+    /// Create a pool's bonus token
+    pub fn pool_bonus_initialize(ctx: Context<PoolBonusInitialize>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.custom_bonus_token = true;
+        pool.custom_bonus_token_mint = ctx.accounts.bonus_mint.key();
+
+        emit_cpi!(BonusPoolInitialized {
+            version: EVENT_VERSION,
+            pool: pool.key(),
+            authority: ctx.accounts.authority.key(),
+            bonus_mint: pool.custom_bonus_token_mint,
+        });
+
+        msg!("🎁 Bonus token initialized: {}", pool.custom_bonus_token_mint);
+        Ok(())
+    }
+
+    /// Fund the bonus underlying account and mint bonus tokens to `recipient`
+    pub fn pool_mint_bonus(ctx: Context<PoolMintBonus>, amount: u64) -> Result<()> {
+        require!(amount > 0, WhiskyError::InvalidAmount);
+
+        let balance_before = ctx.accounts.bonus_underlying_token_account.amount;
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.funder_underlying_ata.to_account_info(),
+                    mint: ctx.accounts.underlying_token_mint.to_account_info(),
+                    to: ctx.accounts.bonus_underlying_token_account.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.underlying_token_mint.decimals,
+        )?;
+        ctx.accounts.bonus_underlying_token_account.reload()?;
+        let bonus_liquidity = ctx.accounts.bonus_underlying_token_account.amount;
+        // One bonus token per underlying token received, net of any transfer fee
+        let received = bonus_liquidity
+            .checked_sub(balance_before)
+            .ok_or(WhiskyError::MathOverflow)?;
+
+        let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
+        let pool_seeds = &[
+            POOL_SEED,
+            underlying_token_mint.as_ref(),
+            ctx.accounts.pool.pool_authority.as_ref(),
+            &[ctx.accounts.pool.bump[0]],
+        ];
+
+        mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.bonus_mint.to_account_info(),
+                    to: ctx.accounts.recipient_bonus_ata.to_account_info(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                },
+                &[&pool_seeds[..]],
+            ),
+            received,
+        )?;
+
+        emit_cpi!(BonusMinted {
+            version: EVENT_VERSION,
+            pool: ctx.accounts.pool.key(),
+            funder: ctx.accounts.funder.key(),
+            recipient: ctx.accounts.recipient.key(),
+            amount: received,
+            bonus_liquidity,
+        });
+
+        msg!("🎁 Bonus minted: {} to {}", received, ctx.accounts.recipient.key());
+        Ok(())
+    }
+
     /// Deposit tokens to provide liquidity
-    pub fn pool_deposit(ctx: Context<PoolDeposit>, amount: u64) -> Result<()> {
+    pub fn pool_deposit(
+        ctx: Context<PoolDeposit>,
+        amount: u64,
+        min_lp_out: u64,
+        native_sol: bool,
+    ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
         require!(whisky_state.pool_deposit_allowed, WhiskyStateError::DepositNotAllowed);
 
+        validate_deposit_whitelist(
+            &ctx.accounts.pool,
+            &ctx.accounts.user.key(),
+            ctx.accounts.whitelist_token_account.as_deref().map(|account| &**account),
+        )?;
+
         let pool_liquidity = ctx.accounts.pool_underlying_token_account.amount;
-        let lp_supply = ctx.accounts.lp_mint.supply;
-        let lp_tokens = calculate_lp_tokens(amount, pool_liquidity, lp_supply);
 
-        transfer(
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
+        if native_sol {
+            wrap_sol(
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                ctx.accounts.user.to_account_info(),
+                user_token_account.clone(),
+                amount,
+            )?;
+        }
+
+        transfer_checked(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_underlying_ata.to_account_info(),
+                TransferChecked {
+                    from: user_token_account.clone(),
+                    mint: ctx.accounts.underlying_token_mint.to_account_info(),
                     to: ctx.accounts.pool_underlying_token_account.to_account_info(),
                     authority: ctx.accounts.user.to_account_info(),
                 },
             ),
             amount,
+            ctx.accounts.underlying_token_mint.decimals,
         )?;
 
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        // Price the deposit on what arrived, net of any transfer fee
+        ctx.accounts.pool_underlying_token_account.reload()?;
+        let received = ctx.accounts.pool_underlying_token_account.amount - pool_liquidity;
+        validate_deposit_limit(&ctx.accounts.pool, pool_liquidity, received)?;
+
+        // Priced on unreserved liquidity, the same basis withdrawals use
+        let available_liquidity = calculate_available_liquidity(pool_liquidity, ctx.accounts.pool.liquidity_checkpoint);
+        let lp_supply = ctx.accounts.lp_mint.supply;
+        let (lp_tokens, locked_lp_tokens) = calculate_deposit_lp_tokens(received, available_liquidity, lp_supply)?;
+        require!(lp_tokens >= min_lp_out, PoolError::SlippageExceeded);
+
         let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
         let pool_seeds = &[
             POOL_SEED,
@@ -191,28 +695,65 @@ pub mod whisky_core {
             lp_tokens,
         )?;
 
-        emit!(PoolChange {
+        if locked_lp_tokens > 0 {
+            mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        to: ctx.accounts.pool_lp_ata.to_account_info(),
+                        authority: ctx.accounts.pool.to_account_info(),
+                    },
+                    &[&pool_seeds[..]],
+                ),
+                locked_lp_tokens,
+            )?;
+        }
+
+        emit_cpi!(PoolChange {
+            version: EVENT_VERSION,
             user: ctx.accounts.user.key(),
             pool: ctx.accounts.pool.key(),
             token_mint: ctx.accounts.underlying_token_mint.key(),
             action: PoolAction::Deposit,
-            amount,
-            post_liquidity: pool_liquidity + amount,
-            lp_supply: lp_supply + lp_tokens,
+            amount: received,
+            fee: 0,
+            post_liquidity: pool_liquidity + received,
+            lp_supply: lp_supply + lp_tokens + locked_lp_tokens,
         });
 
-        msg!("💰 Deposited {} tokens, received {} LP tokens", amount, lp_tokens);
+        msg!("💰 Deposited {} tokens, received {} LP tokens", received, lp_tokens);
         Ok(())
     }
 
     /// Withdraw liquidity from pool
-    pub fn pool_withdraw(ctx: Context<PoolWithdraw>, amount: u64) -> Result<()> {
+    pub fn pool_withdraw(
+        ctx: Context<PoolWithdraw>,
+        amount: u64,
+        min_underlying_out: u64,
+        native_sol: bool,
+    ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
         require!(whisky_state.pool_withdraw_allowed, WhiskyStateError::WithdrawalNotAllowed);
+        // Pools with a withdrawal delay only pay out through queued requests
+        require!(ctx.accounts.pool.withdraw_delay_seconds == 0, PoolError::WithdrawalDelayRequired);
 
+        let config = EffectivePoolConfig::resolve(whisky_state, &ctx.accounts.pool);
         let pool_liquidity = ctx.accounts.pool_underlying_token_account.amount;
         let lp_supply = ctx.accounts.lp_mint.supply;
-        let withdraw_amount = calculate_withdraw_amount(amount, pool_liquidity, lp_supply);
+        let (withdraw_amount, withdraw_fee) = calculate_withdrawal(
+            amount,
+            pool_liquidity,
+            ctx.accounts.pool.liquidity_checkpoint,
+            lp_supply,
+            config.pool_withdraw_fee_bps,
+        );
+        let transfer_fee = current_transfer_fee(&ctx.accounts.underlying_token_mint.to_account_info())?;
+        let received = calculate_net_transfer_amount(transfer_fee.as_ref(), withdraw_amount)?;
+        require!(received >= min_underlying_out, PoolError::SlippageExceeded);
+
+        let now = Clock::get()?.unix_timestamp;
+        record_withdrawal(&mut ctx.accounts.pool, pool_liquidity, withdraw_amount, now)?;
 
         burn(
             CpiContext::new(
@@ -234,20 +775,259 @@ pub mod whisky_core {
             &[ctx.accounts.pool.bump[0]],
         ];
 
-        transfer(
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
+
+        transfer_checked(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.pool_underlying_token_account.to_account_info(),
-                    to: ctx.accounts.user_underlying_ata.to_account_info(),
+                    mint: ctx.accounts.underlying_token_mint.to_account_info(),
+                    to: user_token_account.clone(),
                     authority: ctx.accounts.pool.to_account_info(),
                 },
                 &[&pool_seeds[..]],
             ),
             withdraw_amount,
+            ctx.accounts.underlying_token_mint.decimals,
+        )?;
+
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        emit_cpi!(PoolChange {
+            version: EVENT_VERSION,
+            user: ctx.accounts.user.key(),
+            pool: ctx.accounts.pool.key(),
+            token_mint: ctx.accounts.underlying_token_mint.key(),
+            action: PoolAction::Withdraw,
+            amount: withdraw_amount,
+            fee: withdraw_fee,
+            post_liquidity: pool_liquidity - withdraw_amount,
+            lp_supply: lp_supply - amount,
+        });
+
+        msg!("💸 Withdrew {} tokens (fee {}) for {} LP tokens", withdraw_amount, withdraw_fee, amount);
+        Ok(())
+    }
+
+    /// Queue a withdrawal until the pool's withdrawal delay has passed
+    ///
+    /// Adding to an existing request restarts its delay.
+    pub fn pool_request_withdraw(ctx: Context<PoolRequestWithdraw>, amount: u64) -> Result<()> {
+        let whisky_state = &ctx.accounts.whisky_state;
+        require!(whisky_state.pool_withdraw_allowed, WhiskyStateError::WithdrawalNotAllowed);
+        require!(amount > 0, PoolError::InvalidWithdrawalAmount);
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_lp_ata.to_account_info(),
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.withdraw_request_lp_ata.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.lp_mint.decimals,
+        )?;
+
+        let unlock_timestamp = calculate_unlock_timestamp(
+            Clock::get()?.unix_timestamp,
+            ctx.accounts.pool.withdraw_delay_seconds,
         )?;
 
-        msg!("💸 Withdrew {} tokens for {} LP tokens", withdraw_amount, amount);
+        let withdraw_request = &mut ctx.accounts.withdraw_request;
+        withdraw_request.user = ctx.accounts.user.key();
+        withdraw_request.pool = ctx.accounts.pool.key();
+        withdraw_request.lp_amount = withdraw_request
+            .lp_amount
+            .checked_add(amount)
+            .ok_or(WhiskyError::MathOverflow)?;
+        withdraw_request.unlock_timestamp = unlock_timestamp;
+        withdraw_request.bump = [ctx.bumps.withdraw_request];
+        let lp_amount = withdraw_request.lp_amount;
+
+        emit_cpi!(WithdrawRequested {
+            version: EVENT_VERSION,
+            user: ctx.accounts.user.key(),
+            pool: ctx.accounts.pool.key(),
+            lp_amount,
+            unlock_timestamp,
+        });
+
+        msg!("⏳ Queued withdrawal of {} LP tokens until {}", lp_amount, unlock_timestamp);
+        Ok(())
+    }
+
+    /// Execute a queued withdrawal once unlocked
+    pub fn pool_execute_withdraw(ctx: Context<PoolExecuteWithdraw>, native_sol: bool) -> Result<()> {
+        require!(ctx.accounts.whisky_state.pool_withdraw_allowed, WhiskyStateError::WithdrawalNotAllowed);
+
+        let withdraw_request = &ctx.accounts.withdraw_request;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= withdraw_request.unlock_timestamp, PoolError::WithdrawalLocked);
+
+        let amount = withdraw_request.lp_amount;
+        let config = EffectivePoolConfig::resolve(&ctx.accounts.whisky_state, &ctx.accounts.pool);
+        let pool_liquidity = ctx.accounts.pool_underlying_token_account.amount;
+        let lp_supply = ctx.accounts.lp_mint.supply;
+        let (withdraw_amount, withdraw_fee) = calculate_withdrawal(
+            amount,
+            pool_liquidity,
+            ctx.accounts.pool.liquidity_checkpoint,
+            lp_supply,
+            config.pool_withdraw_fee_bps,
+        );
+        record_withdrawal(&mut ctx.accounts.pool, pool_liquidity, withdraw_amount, now)?;
+
+        let pool_key = ctx.accounts.pool.key();
+        let user_key = ctx.accounts.user.key();
+        let withdraw_request = &ctx.accounts.withdraw_request;
+        let request_seeds = &[
+            WITHDRAW_REQUEST_SEED,
+            pool_key.as_ref(),
+            user_key.as_ref(),
+            &[withdraw_request.bump[0]],
+        ];
+
+        burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    from: ctx.accounts.withdraw_request_lp_ata.to_account_info(),
+                    authority: ctx.accounts.withdraw_request.to_account_info(),
+                },
+                &[&request_seeds[..]],
+            ),
+            amount,
+        )?;
+
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.withdraw_request_lp_ata.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
+                authority: ctx.accounts.withdraw_request.to_account_info(),
+            },
+            &[&request_seeds[..]],
+        ))?;
+
+        let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
+        let pool_seeds = &[
+            POOL_SEED,
+            underlying_token_mint.as_ref(),
+            ctx.accounts.pool.pool_authority.as_ref(),
+            &[ctx.accounts.pool.bump[0]],
+        ];
+
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.pool_underlying_token_account.to_account_info(),
+                    mint: ctx.accounts.underlying_token_mint.to_account_info(),
+                    to: user_token_account.clone(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                },
+                &[&pool_seeds[..]],
+            ),
+            withdraw_amount,
+            ctx.accounts.underlying_token_mint.decimals,
+        )?;
+
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        emit_cpi!(PoolChange {
+            version: EVENT_VERSION,
+            user: user_key,
+            pool: pool_key,
+            token_mint: underlying_token_mint,
+            action: PoolAction::Withdraw,
+            amount: withdraw_amount,
+            fee: withdraw_fee,
+            post_liquidity: pool_liquidity - withdraw_amount,
+            lp_supply: lp_supply - amount,
+        });
+
+        msg!("💸 Executed queued withdrawal of {} tokens (fee {}) for {} LP tokens", withdraw_amount, withdraw_fee, amount);
+        Ok(())
+    }
+
+    /// Cancel a queued withdrawal, returning its LP tokens
+    pub fn pool_cancel_withdraw(ctx: Context<PoolCancelWithdraw>) -> Result<()> {
+        let amount = ctx.accounts.withdraw_request.lp_amount;
+        let pool_key = ctx.accounts.pool.key();
+        let user_key = ctx.accounts.user.key();
+        let request_seeds = &[
+            WITHDRAW_REQUEST_SEED,
+            pool_key.as_ref(),
+            user_key.as_ref(),
+            &[ctx.accounts.withdraw_request.bump[0]],
+        ];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.withdraw_request_lp_ata.to_account_info(),
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.user_lp_ata.to_account_info(),
+                    authority: ctx.accounts.withdraw_request.to_account_info(),
+                },
+                &[&request_seeds[..]],
+            ),
+            amount,
+            ctx.accounts.lp_mint.decimals,
+        )?;
+
+        close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.withdraw_request_lp_ata.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
+                authority: ctx.accounts.withdraw_request.to_account_info(),
+            },
+            &[&request_seeds[..]],
+        ))?;
+
+        emit_cpi!(WithdrawCancelled {
+            version: EVENT_VERSION,
+            user: user_key,
+            pool: pool_key,
+            lp_amount: amount,
+        });
+
+        msg!("↩️ Cancelled queued withdrawal of {} LP tokens", amount);
         Ok(())
     }
 
@@ -288,33 +1068,74 @@ pub mod whisky_core {
         game.points_authority = Pubkey::default();
         game.metadata = String::new();
 
+        emit_cpi!(PlayerInitialized {
+            version: EVENT_VERSION,
+            user: ctx.accounts.user.key(),
+            player: ctx.accounts.player.key(),
+            game: ctx.accounts.game.key(),
+        });
+
         msg!("🎮 Player initialized for {}", ctx.accounts.user.key());
         Ok(())
     }
 
     /// Place a bet and start a game
+    #[allow(clippy::too_many_arguments)]
     pub fn play_game(
         ctx: Context<PlayGame>,
         wager: u64,
         bet: Vec<u32>,
         client_seed: String,
+        expected_rng_seed_hashed: String,
         creator_fee_bps: u32,
         jackpot_fee_bps: u32,
         metadata: String,
+        native_sol: bool,
     ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
-        let pool = &ctx.accounts.pool;
+        let pool = &mut ctx.accounts.pool;
         let player = &mut ctx.accounts.player;
         let game = &mut ctx.accounts.game;
         
         require!(whisky_state.playing_allowed, WhiskyStateError::PlaysNotAllowed);
+        require!(game.status != GameStatus::ResultRequested, PlayerError::GameInProgress);
+        require!(!game.next_rng_seed_hashed.is_empty(), RngError::SeedHashNotCommitted);
+        // The player bets against the commitment they saw, not one swapped in since
+        require!(
+            expected_rng_seed_hashed.eq_ignore_ascii_case(&game.next_rng_seed_hashed),
+            RngError::SeedHashMismatch
+        );
+        
+        let config = EffectivePoolConfig::resolve(whisky_state, pool);
         
         validate_bet(&bet)?;
-        validate_wager(wager, pool.min_wager)?;
-        validate_house_edge(&bet, whisky_state.max_house_edge_bps)?;
+        validate_wager(wager, config.min_wager)?;
+        validate_house_edge(&bet, config.max_house_edge_bps)?;
         
         let pool_liquidity = ctx.accounts.pool_underlying_token_account.amount;
-        validate_max_payout(&bet, wager, pool_liquidity, whisky_state.max_payout_bps)?;
+        let available_liquidity = calculate_available_liquidity(pool_liquidity, pool.liquidity_checkpoint);
+        validate_max_payout(&bet, wager, available_liquidity, config.max_payout_bps)?;
+        validate_fees(
+            &[creator_fee_bps as u64, jackpot_fee_bps as u64, config.whisky_fee_bps, config.pool_fee_bps],
+            config.max_creator_fee_bps,
+            config.max_jackpot_fee_bps,
+            config.max_total_fee_bps,
+        )?;
+
+        let bonus_balance = match (&ctx.accounts.bonus_mint, &ctx.accounts.user_bonus_ata) {
+            (Some(bonus_mint), Some(user_bonus_ata)) if pool.custom_bonus_token => {
+                require_keys_eq!(user_bonus_ata.mint, bonus_mint.key(), WhiskyError::InvalidMint);
+                user_bonus_ata.amount
+            }
+            _ => 0,
+        };
+        let (bonus_used, underlying_used) = calculate_bonus_usage(wager, bonus_balance);
+
+        // Hold back the worst-case payout until the game settles or is refunded
+        pool.liquidity_checkpoint = pool
+            .liquidity_checkpoint
+            .checked_add(calculate_max_payout(&bet, wager)?)
+            .ok_or(WhiskyError::MathOverflow)?;
 
         player.nonce = player.nonce.checked_add(1).unwrap();
         game.nonce = player.nonce;
@@ -325,36 +1146,143 @@ pub mod whisky_core {
         game.timestamp = Clock::get()?.unix_timestamp;
         game.creator = ctx.accounts.creator.key();
         game.wager = wager;
+        game.underlying_used = underlying_used;
+        game.bonus_used = bonus_used;
+        game.jackpot_result = 0;
+        game.jackpot_payout = 0;
         game.creator_fee = calculate_fee(wager, creator_fee_bps as u64);
-        game.whisky_fee = calculate_fee(wager, whisky_state.whisky_fee_bps);
+        game.whisky_fee = calculate_fee(wager, config.whisky_fee_bps);
+        game.pool_fee = calculate_fee(wager, config.pool_fee_bps);
         game.jackpot_fee = calculate_fee(wager, jackpot_fee_bps as u64);
+        game.anti_spam_fee = config.anti_spam_fee;
         game.client_seed = client_seed;
         game.bet = bet;
         game.metadata = metadata;
-        game.jackpot_probability_ubps = calculate_jackpot_probability(wager, pool_liquidity);
+        // Odds are priced on the jackpot vault alone, not the pool's liquidity
+        let jackpot_vault_balance = ctx.accounts.pool_jackpot_token_account.amount;
+        game.jackpot_probability_ubps = calculate_jackpot_probability(
+            game.jackpot_fee,
+            jackpot_vault_balance,
+            whisky_state.jackpot_payout_to_user_bps,
+            &config,
+        );
+
+        let escrow_amount = calculate_escrow_amount(
+            underlying_used,
+            &[game.creator_fee, game.whisky_fee, game.pool_fee, game.jackpot_fee],
+        )?;
+        // The player covers any transfer fee so the escrow holds the full amount
+        let transfer_fee = current_transfer_fee(&ctx.accounts.underlying_token_mint.to_account_info())?;
+        let transfer_amount = calculate_gross_transfer_amount(transfer_fee.as_ref(), escrow_amount)?;
+
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
+        if native_sol {
+            wrap_sol(
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                ctx.accounts.user.to_account_info(),
+                user_token_account.clone(),
+                transfer_amount,
+            )?;
+        }
 
-        transfer(
+        transfer_checked(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_underlying_ata.to_account_info(),
+                TransferChecked {
+                    from: user_token_account.clone(),
+                    mint: ctx.accounts.underlying_token_mint.to_account_info(),
                     to: ctx.accounts.player_ata.to_account_info(),
                     authority: ctx.accounts.user.to_account_info(),
                 },
             ),
-            wager,
+            transfer_amount,
+            ctx.accounts.underlying_token_mint.decimals,
         )?;
 
-        msg!("🎲 Game started! Wager: {}, Nonce: {}", wager, game.nonce);
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        if game.anti_spam_fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: game.to_account_info(),
+                    },
+                ),
+                game.anti_spam_fee,
+            )?;
+        }
+
+        if let (Some(bonus_mint), Some(user_bonus_ata)) = (&ctx.accounts.bonus_mint, &ctx.accounts.user_bonus_ata) {
+            if bonus_used > 0 {
+                burn(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Burn {
+                            mint: bonus_mint.to_account_info(),
+                            from: user_bonus_ata.to_account_info(),
+                            authority: ctx.accounts.user.to_account_info(),
+                        },
+                    ),
+                    bonus_used,
+                )?;
+            }
+        }
+
+        emit_cpi!(GameStarted {
+            version: EVENT_VERSION,
+            user: game.user,
+            pool: game.pool,
+            token_mint: game.token_mint,
+            creator: game.creator,
+            nonce: game.nonce,
+            wager,
+            bonus_used,
+            creator_fee: game.creator_fee,
+            whisky_fee: game.whisky_fee,
+            pool_fee: game.pool_fee,
+            jackpot_fee: game.jackpot_fee,
+            anti_spam_fee: game.anti_spam_fee,
+            jackpot_probability_ubps: game.jackpot_probability_ubps,
+            client_seed: game.client_seed.clone(),
+            bet: game.bet.clone(),
+            rng_seed_hashed: game.next_rng_seed_hashed.clone(),
+            metadata: game.metadata.clone(),
+        });
+
+        msg!("🎲 Game started! Wager: {}, Bonus: {}, Fees: {}, Nonce: {}",
+             wager, bonus_used, escrow_amount - underlying_used, game.nonce);
         Ok(())
     }
 
     /// Claim winnings after game settlement
-    pub fn player_claim(ctx: Context<PlayerClaim>) -> Result<()> {
+    pub fn player_claim(ctx: Context<PlayerClaim>, native_sol: bool) -> Result<()> {
         let player = &ctx.accounts.player;
         let game = &ctx.accounts.game;
         
-        require!(game.status == GameStatus::Ready, PlayerError::NotReadyToPlay);
+        validate_claim(&game.status)?;
+
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
 
         let user_key = ctx.accounts.user.key();
         let player_seeds = &[
@@ -364,26 +1292,168 @@ pub mod whisky_core {
         ];
 
         if ctx.accounts.player_ata.amount > 0 {
-            transfer(
+            transfer_checked(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.player_ata.to_account_info(),
-                        to: ctx.accounts.user_underlying_ata.to_account_info(),
+                        mint: ctx.accounts.underlying_token_mint.to_account_info(),
+                        to: user_token_account.clone(),
                         authority: ctx.accounts.player.to_account_info(),
                     },
                     &[&player_seeds[..]],
                 ),
                 ctx.accounts.player_ata.amount,
+                ctx.accounts.underlying_token_mint.decimals,
             )?;
         }
 
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        emit_cpi!(WinningsClaimed {
+            version: EVENT_VERSION,
+            user: user_key,
+            token_mint: ctx.accounts.underlying_token_mint.key(),
+            nonce: game.nonce,
+            amount: ctx.accounts.player_ata.amount,
+        });
+
         msg!("💰 Winnings claimed: {}", ctx.accounts.player_ata.amount);
         Ok(())
     }
 
+    /// Refund a game the RNG failed to settle within the timeout, callable by anyone
+    pub fn game_refund(ctx: Context<GameRefund>, native_sol: bool) -> Result<()> {
+        let whisky_state = &ctx.accounts.whisky_state;
+        let player = &ctx.accounts.player;
+        let game = &ctx.accounts.game;
+
+        require!(game.status == GameStatus::ResultRequested, PlayerError::InvalidGameState);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            is_rng_timed_out(game.timestamp, whisky_state.rng_timeout_seconds, now),
+            RngError::TimeoutNotReached
+        );
+
+        let refund_amount = calculate_escrow_amount(
+            game.underlying_used,
+            &[game.creator_fee, game.whisky_fee, game.pool_fee, game.jackpot_fee],
+        )?;
+        let user_key = ctx.accounts.user.key();
+        let player_seeds = &[
+            PLAYER_SEED,
+            user_key.as_ref(),
+            &[player.bump[0]],
+        ];
+
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
+        let user_token_account = user_token_account(
+            native_sol,
+            &ctx.accounts.user_underlying_ata,
+            &ctx.accounts.wsol_temp_account,
+        )?;
+        // The temporary account's rent goes back to the player with the SOL,
+        // so only they can take a refund as native SOL
+        require!(
+            !native_sol || ctx.accounts.payer.key() == ctx.accounts.user.key(),
+            WhiskyError::Unauthorized
+        );
+
+        transfer_from_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.underlying_token_mint,
+            ctx.accounts.player_ata.to_account_info(),
+            user_token_account.clone(),
+            ctx.accounts.player.to_account_info(),
+            &[&player_seeds[..]],
+            refund_amount,
+        )?;
+
+        if native_sol {
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                &[],
+            )?;
+        }
+
+        // Bonus tokens burned by the wager are minted back
+        let bonus_refunded = game.bonus_used;
+        if bonus_refunded > 0 {
+            let bonus_mint = ctx.accounts.bonus_mint.as_ref().ok_or(WhiskyError::InvalidAccount)?;
+            let user_bonus_ata = ctx.accounts.user_bonus_ata.as_ref().ok_or(WhiskyError::InvalidAccount)?;
+            require_keys_eq!(user_bonus_ata.mint, bonus_mint.key(), WhiskyError::InvalidMint);
+
+            let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
+            let pool_seeds = &[
+                POOL_SEED,
+                underlying_token_mint.as_ref(),
+                ctx.accounts.pool.pool_authority.as_ref(),
+                &[ctx.accounts.pool.bump[0]],
+            ];
+
+            mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: bonus_mint.to_account_info(),
+                        to: user_bonus_ata.to_account_info(),
+                        authority: ctx.accounts.pool.to_account_info(),
+                    },
+                    &[&pool_seeds[..]],
+                ),
+                bonus_refunded,
+            )?;
+        }
+
+        let game = &ctx.accounts.game;
+        let max_payout = calculate_max_payout(&game.bet, game.wager)?;
+        let pool = &mut ctx.accounts.pool;
+        pool.liquidity_checkpoint = pool.liquidity_checkpoint.saturating_sub(max_payout);
+
+        let game = &mut ctx.accounts.game;
+        let anti_spam_fee = game.anti_spam_fee;
+        game.status = GameStatus::None;
+        game.anti_spam_fee = 0;
+
+        emit_cpi!(GameRefunded {
+            version: EVENT_VERSION,
+            user: game.user,
+            pool: game.pool,
+            token_mint: game.token_mint,
+            nonce: game.nonce,
+            wager: game.wager,
+            refunded: refund_amount,
+            bonus_refunded,
+            timestamp: now,
+        });
+
+        // Lamports move directly only once every CPI has run
+        ctx.accounts.game.sub_lamports(anti_spam_fee)?;
+        ctx.accounts.user.add_lamports(anti_spam_fee)?;
+
+        msg!("↩️ Game refunded: {}", refund_amount);
+        Ok(())
+    }
+
     /// Close player account
     pub fn player_close(ctx: Context<PlayerClose>) -> Result<()> {
+        emit_cpi!(PlayerClosed {
+            version: EVENT_VERSION,
+            user: ctx.accounts.user.key(),
+        });
+
         msg!("👋 Player account closed");
         Ok(())
     }
@@ -395,37 +1465,175 @@ pub mod whisky_core {
         next_rng_seed_hashed: String,
     ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
-        let game = &mut ctx.accounts.game;
+        let game = &ctx.accounts.game;
         
-        require!(ctx.accounts.rng.key() == whisky_state.rng_address, WhiskyError::Unauthorized);
+        require!(
+            is_rng_authority(whisky_state, &ctx.accounts.rng.key(), Clock::get()?.unix_timestamp),
+            WhiskyError::Unauthorized
+        );
         require!(game.status == GameStatus::ResultRequested, RngError::ResultNotRequested);
+        require!(
+            !is_rng_timed_out(game.timestamp, whisky_state.rng_timeout_seconds, Clock::get()?.unix_timestamp),
+            RngError::RngTimeout
+        );
+        verify_rng_seed(&rng_seed, &game.next_rng_seed_hashed)?;
+        validate_seed_hash(&next_rng_seed_hashed)?;
 
         let result_index = calculate_game_result(&rng_seed, &game.client_seed, game.nonce, &game.bet);
-        game.result = result_index;
-        game.rng_seed = rng_seed.clone();
-        game.next_rng_seed_hashed = next_rng_seed_hashed.clone();
-        game.status = GameStatus::Ready;
-
         let game_hash = get_game_hash(&rng_seed, &game.client_seed, game.nonce);
         let jackpot_won = calculate_jackpot_result(&game_hash, game.jackpot_probability_ubps);
-        
-        if jackpot_won {
-            game.jackpot_payout = ctx.accounts.pool_jackpot_token_account.amount;
+
+        let multiplier = calculate_multiplier(&game.bet, result_index as usize);
+        let max_payout = calculate_max_payout(&game.bet, game.wager)?;
+        let wager = game.wager;
+        let base_payout = calculate_payout(wager, multiplier)?;
+        let pool_amount = game
+            .underlying_used
+            .checked_add(game.pool_fee)
+            .ok_or(WhiskyError::MathOverflow)?;
+        let [bonus_to_jackpot, payout_from_bonus, bonus_to_pool] = calculate_bonus_settlement(
+            game.bonus_used,
+            base_payout,
+            whisky_state.bonus_to_jackpot_ratio_bps,
+        )?;
+        let payout_from_pool = base_payout - payout_from_bonus;
+        let creator_fee = game.creator_fee;
+        let whisky_fee = game.whisky_fee;
+        let jackpot_fee = game.jackpot_fee;
+        let jackpot_split_bps = [
+            whisky_state.jackpot_payout_to_user_bps,
+            whisky_state.jackpot_payout_to_creator_bps,
+            whisky_state.jackpot_payout_to_pool_bps,
+            whisky_state.jackpot_payout_to_whisky_bps,
+        ];
+
+        let user_key = game.user;
+        let player_seeds = &[
+            PLAYER_SEED,
+            user_key.as_ref(),
+            &[ctx.accounts.player.bump[0]],
+        ];
+
+        let underlying_token_mint = ctx.accounts.underlying_token_mint.key();
+        let pool_seeds = &[
+            POOL_SEED,
+            underlying_token_mint.as_ref(),
+            ctx.accounts.pool.pool_authority.as_ref(),
+            &[ctx.accounts.pool.bump[0]],
+        ];
+
+        // Empty the escrow first: the wager and pool fee go to the pool so
+        // winnings can be paid out of it, the other fees to their recipients
+        let token_program = &ctx.accounts.token_program;
+        let player_ata = ctx.accounts.player_ata.to_account_info();
+        let player_authority = ctx.accounts.player.to_account_info();
+        for (destination, amount) in [
+            (ctx.accounts.pool_underlying_token_account.to_account_info(), pool_amount),
+            (ctx.accounts.creator_ata.to_account_info(), creator_fee),
+            (ctx.accounts.whisky_state_ata.to_account_info(), whisky_fee),
+            (ctx.accounts.pool_jackpot_token_account.to_account_info(), jackpot_fee),
+        ] {
+            transfer_from_pda(
+                token_program,
+                &ctx.accounts.underlying_token_mint,
+                player_ata.clone(),
+                destination,
+                player_authority.clone(),
+                &[&player_seeds[..]],
+                amount,
+            )?;
         }
 
-        let bet_weight = game.bet.get(result_index as usize).copied().unwrap_or(0);
-        let total_weight: u64 = game.bet.iter().map(|&x| x as u64).sum();
-        
-        let multiplier = if bet_weight > 0 && total_weight > 0 {
-            (total_weight * BPS_PER_WHOLE) / (bet_weight as u64)
+        if ctx.accounts.game.bonus_used > 0 {
+            let bonus_underlying_token_account = ctx
+                .accounts
+                .bonus_underlying_token_account
+                .as_ref()
+                .ok_or(WhiskyError::InvalidAccount)?
+                .to_account_info();
+            for (destination, amount) in [
+                (ctx.accounts.pool_jackpot_token_account.to_account_info(), bonus_to_jackpot),
+                (player_ata.clone(), payout_from_bonus),
+                (ctx.accounts.pool_underlying_token_account.to_account_info(), bonus_to_pool),
+            ] {
+                transfer_from_pda(
+                    token_program,
+                    &ctx.accounts.underlying_token_mint,
+                    bonus_underlying_token_account.clone(),
+                    destination,
+                    ctx.accounts.pool.to_account_info(),
+                    &[&pool_seeds[..]],
+                    amount,
+                )?;
+            }
+        }
+
+        ctx.accounts.pool_underlying_token_account.reload()?;
+        ctx.accounts.pool_jackpot_token_account.reload()?;
+
+        // A win pays out the whole jackpot, split between the player, the
+        // game creator, the pool and the protocol
+        let jackpot_payout = if jackpot_won {
+            ctx.accounts.pool_jackpot_token_account.amount
         } else {
             0
         };
+        let [jackpot_to_user, jackpot_to_creator, jackpot_to_pool, jackpot_to_whisky] =
+            calculate_jackpot_split(jackpot_payout, &jackpot_split_bps)?;
 
-        let base_payout = (game.wager * multiplier) / BPS_PER_WHOLE;
-        let total_payout = base_payout + game.jackpot_payout;
+        require!(
+            ctx.accounts.pool_underlying_token_account.amount >= payout_from_pool,
+            PoolError::InsufficientLiquidity
+        );
 
-        emit!(GameSettled {
+        transfer_from_pda(
+            token_program,
+            &ctx.accounts.underlying_token_mint,
+            ctx.accounts.pool_underlying_token_account.to_account_info(),
+            player_ata.clone(),
+            ctx.accounts.pool.to_account_info(),
+            &[&pool_seeds[..]],
+            payout_from_pool,
+        )?;
+
+        for (destination, amount) in [
+            (player_ata, jackpot_to_user),
+            (ctx.accounts.creator_ata.to_account_info(), jackpot_to_creator),
+            (ctx.accounts.pool_underlying_token_account.to_account_info(), jackpot_to_pool),
+            (ctx.accounts.whisky_state_ata.to_account_info(), jackpot_to_whisky),
+        ] {
+            transfer_from_pda(
+                token_program,
+                &ctx.accounts.underlying_token_mint,
+                ctx.accounts.pool_jackpot_token_account.to_account_info(),
+                destination,
+                ctx.accounts.pool.to_account_info(),
+                &[&pool_seeds[..]],
+                amount,
+            )?;
+        }
+
+        ctx.accounts.pool_underlying_token_account.reload()?;
+        let pool_liquidity = ctx.accounts.pool_underlying_token_account.amount;
+        let total_payout = base_payout
+            .checked_add(jackpot_to_user)
+            .ok_or(WhiskyError::MathOverflow)?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.liquidity_checkpoint = pool.liquidity_checkpoint.saturating_sub(max_payout);
+
+        let game = &mut ctx.accounts.game;
+        let anti_spam_fee = game.anti_spam_fee;
+        game.anti_spam_fee = 0;
+        game.result = result_index;
+        game.rng_seed = rng_seed.clone();
+        game.next_rng_seed_hashed = next_rng_seed_hashed.clone();
+        game.status = GameStatus::Ready;
+        game.jackpot_result = if jackpot_won { 1 } else { 0 };
+        game.jackpot_payout = jackpot_payout;
+
+        emit_cpi!(GameSettled {
+            version: EVENT_VERSION,
             user: game.user,
             pool: game.pool,
             token_mint: game.token_mint,
@@ -439,21 +1647,28 @@ pub mod whisky_core {
             wager: game.wager,
             payout: total_payout,
             multiplier_bps: multiplier as u32,
-            payout_from_bonus_pool: 0,
-            payout_from_normal_pool: base_payout,
+            payout_from_bonus_pool: payout_from_bonus,
+            payout_from_normal_pool: payout_from_pool,
             jackpot_probability_ubps: game.jackpot_probability_ubps,
-            jackpot_result: if jackpot_won { 1 } else { 0 },
+            jackpot_result: game.jackpot_result,
             nonce: game.nonce,
             client_seed: game.client_seed.clone(),
             result_index,
             bet: game.bet.clone(),
-            jackpot_payout_to_user: game.jackpot_payout,
-            pool_liquidity: ctx.accounts.pool_underlying_token_account.amount,
+            jackpot_payout_to_user: jackpot_to_user,
+            jackpot_payout_to_creator: jackpot_to_creator,
+            jackpot_payout_to_pool: jackpot_to_pool,
+            jackpot_payout_to_whisky: jackpot_to_whisky,
+            pool_liquidity,
             rng_seed: rng_seed.clone(),
             next_rng_seed_hashed: next_rng_seed_hashed.clone(),
             metadata: game.metadata.clone(),
         });
 
+        // Lamports move directly only once every CPI has run
+        ctx.accounts.game.sub_lamports(anti_spam_fee)?;
+        ctx.accounts.user.add_lamports(anti_spam_fee)?;
+
         msg!("🎯 Game settled! Result: {}, Payout: {}, Jackpot: {}", 
              result_index, total_payout, jackpot_won);
         Ok(())
@@ -465,14 +1680,71 @@ pub mod whisky_core {
         next_rng_seed_hashed: String,
     ) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
-        require!(ctx.accounts.rng.key() == whisky_state.rng_address, WhiskyError::Unauthorized);
+        require!(
+            is_rng_authority(whisky_state, &ctx.accounts.rng.key(), Clock::get()?.unix_timestamp),
+            WhiskyError::Unauthorized
+        );
+        // Replacing the commitment mid-game would let the RNG pick a seed
+        // after seeing the player's bet
+        require!(
+            ctx.accounts.game.status != GameStatus::ResultRequested,
+            PlayerError::GameInProgress
+        );
+        validate_seed_hash(&next_rng_seed_hashed)?;
         
-        ctx.accounts.game.next_rng_seed_hashed = next_rng_seed_hashed;
+        ctx.accounts.game.next_rng_seed_hashed = next_rng_seed_hashed.clone();
+
+        emit_cpi!(RngSeedHashProvided {
+            version: EVENT_VERSION,
+            user: ctx.accounts.game.user,
+            rng: ctx.accounts.rng.key(),
+            next_rng_seed_hashed,
+        });
+        Ok(())
+    }
+
+    /// Forfeit an abandoned game's anti-spam fee to the RNG
+    pub fn rng_forfeit_anti_spam_fee(ctx: Context<RngForfeitAntiSpamFee>) -> Result<()> {
+        let whisky_state = &ctx.accounts.whisky_state;
+        let game = &ctx.accounts.game;
+
+        require!(
+            is_rng_authority(whisky_state, &ctx.accounts.rng.key(), Clock::get()?.unix_timestamp),
+            WhiskyError::Unauthorized
+        );
+        require!(game.status == GameStatus::ResultRequested, RngError::ResultNotRequested);
+        require!(
+            is_game_abandoned(game.timestamp, whisky_state.rng_timeout_seconds, Clock::get()?.unix_timestamp),
+            RngError::TimeoutNotReached
+        );
+
+        // Only the lamports are forfeited; the player can still refund the
+        // escrowed wager and fees afterwards
+        let amount = game.anti_spam_fee;
+        require!(amount > 0, WhiskyError::InvalidAmount);
+
+        let game = &mut ctx.accounts.game;
+        game.anti_spam_fee = 0;
+
+        emit_cpi!(AntiSpamFeeForfeited {
+            version: EVENT_VERSION,
+            user: game.user,
+            pool: game.pool,
+            nonce: game.nonce,
+            rng: ctx.accounts.rng.key(),
+            amount,
+        });
+
+        // Lamports move directly only once every CPI has run
+        ctx.accounts.game.sub_lamports(amount)?;
+        ctx.accounts.rng.add_lamports(amount)?;
+
+        msg!("🚫 Anti-spam fee forfeited: {}", amount);
         Ok(())
     }
 
     /// Distribute protocol fees
-    pub fn distribute_fees(ctx: Context<DistributeFees>, _native_sol: bool) -> Result<()> {
+    pub fn distribute_fees(ctx: Context<DistributeFees>, native_sol: bool) -> Result<()> {
         let whisky_state = &ctx.accounts.whisky_state;
         let amount = ctx.accounts.whisky_state_ata.amount;
         
@@ -480,22 +1752,79 @@ pub mod whisky_core {
             return Ok(());
         }
 
+        validate_native_sol(native_sol, &ctx.accounts.underlying_token_mint.key())?;
         let seeds = &[WHISKY_STATE_SEED, &[whisky_state.bump[0]]];
 
-        transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.whisky_state_ata.to_account_info(),
-                    to: ctx.accounts.distribution_recipient_ata.to_account_info(),
-                    authority: ctx.accounts.whisky_state.to_account_info(),
-                },
+        if native_sol {
+            // Unwrap through a temporary account: pending games settle into
+            // the fee account, so it must stay open
+            let fee_unwrap_account = ctx
+                .accounts
+                .fee_unwrap_account
+                .as_ref()
+                .ok_or(WhiskyError::InvalidAccount)?
+                .to_account_info();
+
+            transfer_from_pda(
+                &ctx.accounts.token_program,
+                &ctx.accounts.underlying_token_mint,
+                ctx.accounts.whisky_state_ata.to_account_info(),
+                fee_unwrap_account.clone(),
+                ctx.accounts.whisky_state.to_account_info(),
                 &[&seeds[..]],
-            ),
+                amount,
+            )?;
+            unwrap_sol(
+                &ctx.accounts.token_program,
+                fee_unwrap_account,
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.whisky_state.to_account_info(),
+                &[&seeds[..]],
+            )?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.distribution_recipient.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        } else {
+            let distribution_recipient_ata = ctx
+                .accounts
+                .distribution_recipient_ata
+                .as_ref()
+                .ok_or(WhiskyError::InvalidAccount)?;
+
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.whisky_state_ata.to_account_info(),
+                        mint: ctx.accounts.underlying_token_mint.to_account_info(),
+                        to: distribution_recipient_ata.to_account_info(),
+                        authority: ctx.accounts.whisky_state.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                amount,
+                ctx.accounts.underlying_token_mint.decimals,
+            )?;
+        }
+
+        emit_cpi!(FeesDistributed {
+            version: EVENT_VERSION,
+            token_mint: ctx.accounts.underlying_token_mint.key(),
+            recipient: whisky_state.distribution_recipient,
             amount,
-        )?;
+        });
 
         msg!("💸 Distributed {} tokens in fees", amount);
         Ok(())
     }
-} 
\ No newline at end of file
+}
+}
+
+pub use processor::*;
@@ -3,12 +3,13 @@ use crate::constants::*;
 
 /// Global protocol state account
 #[account]
+#[derive(Default)]
 pub struct WhiskyState {
     /// Protocol authority (admin)
     pub authority: Pubkey,
     /// RNG provider address
     pub rng_address: Pubkey,
-    /// Secondary RNG provider address  
+    /// Failover RNG provider address, also accepted for settlement
     pub rng_address_2: Pubkey,
     /// Anti-spam fee amount
     pub anti_spam_fee: u64,
@@ -48,19 +49,56 @@ pub struct WhiskyState {
     pub distribution_recipient: Pubkey,
     /// PDA bump seed
     pub bump: [u8; 1],
+    /// Seconds after play before an unsettled game can be refunded
+    pub rng_timeout_seconds: i64,
+    /// Maximum jackpot contribution allowed (BPS)
+    pub max_jackpot_fee_bps: u64,
+    /// Maximum combined fees charged on a wager (BPS)
+    pub max_total_fee_bps: u64,
+    /// Whether only approved mints can get new pools
+    pub pool_creation_restricted: bool,
+    /// Jackpot odds while the jackpot is empty (ubps)
+    pub jackpot_base_probability_ubps: u64,
+    /// Upper bound on jackpot odds (ubps)
+    pub jackpot_max_probability_ubps: u64,
+    /// Share of a player's fair jackpot expected value withheld (BPS)
+    pub jackpot_edge_bps: u64,
+    /// RNG key staged to replace `rng_address`
+    pub pending_rng_address: Pubkey,
+    /// Timestamp from which the staged RNG key is active
+    pub pending_rng_activation_timestamp: i64,
 }
 
 impl WhiskyState {
     /// Calculate space needed for WhiskyState account
     pub const SPACE: usize = DISCRIMINATOR_SIZE 
-        + PUBKEY_SIZE * 4  // authority, rng_address, rng_address_2, distribution_recipient
-        + U64_SIZE * 12    // fee configurations
-        + BOOL_SIZE * 4    // permission flags
+        + PUBKEY_SIZE * 5  // authority, rng_address, rng_address_2, distribution_recipient, pending_rng_address
+        + U64_SIZE * 18    // fee and jackpot odds configurations
+        + BOOL_SIZE * 5    // permission flags
+        + I64_SIZE * 2     // rng_timeout_seconds, pending_rng_activation_timestamp
+        + 1;               // bump
+}
+
+/// Marks an underlying mint as approved for pool creation while pool
+/// creation is restricted
+#[account]
+pub struct ApprovedMint {
+    /// Approved underlying token mint
+    pub mint: Pubkey,
+    /// PDA bump seed
+    pub bump: [u8; 1],
+}
+
+impl ApprovedMint {
+    /// Calculate space needed for ApprovedMint account
+    pub const SPACE: usize = DISCRIMINATOR_SIZE
+        + PUBKEY_SIZE      // mint
         + 1;               // bump
 }
 
 /// Gaming pool account
 #[account]
+#[derive(Default)]
 pub struct Pool {
     /// Pool authority (creator)
     pub pool_authority: Pubkey,
@@ -74,7 +112,7 @@ pub struct Pool {
     pub min_wager: u64,
     /// Number of games played
     pub plays: u64,
-    /// Liquidity checkpoint
+    /// Liquidity reserved for the maximum payout of unsettled games
     pub liquidity_checkpoint: u64,
     /// Whether deposit limit is enabled
     pub deposit_limit: bool,
@@ -92,9 +130,9 @@ pub struct Pool {
     pub custom_max_payout: bool,
     /// Custom max payout in BPS
     pub custom_max_payout_bps: u64,
-    /// Custom bonus token mint
+    /// Bonus token mint, redeemable against the pool's bonus underlying account
     pub custom_bonus_token_mint: Pubkey,
-    /// Whether custom bonus token is enabled
+    /// Whether the bonus token has been initialized
     pub custom_bonus_token: bool,
     /// Whether custom max creator fee is enabled
     pub custom_max_creator_fee: bool,
@@ -106,17 +144,160 @@ pub struct Pool {
     pub deposit_whitelist_address: Pubkey,
     /// PDA bump seed
     pub bump: [u8; 1],
+    /// Whether custom max jackpot fee is enabled
+    pub custom_max_jackpot_fee: bool,
+    /// Custom max jackpot fee in BPS
+    pub custom_max_jackpot_fee_bps: u64,
+    /// Whether custom withdrawal fee is enabled
+    pub custom_pool_withdraw_fee: bool,
+    /// Custom withdrawal fee in BPS
+    pub custom_pool_withdraw_fee_bps: u64,
+    /// Whether withdrawals are capped per window
+    pub withdraw_limit: bool,
+    /// Share of window-start liquidity that may leave per window (BPS)
+    pub withdraw_limit_bps: u64,
+    /// Withdrawal window length in seconds
+    pub withdraw_limit_window_seconds: i64,
+    /// Timestamp the current withdrawal window opened
+    pub withdraw_window_start: i64,
+    /// Pool liquidity when the current withdrawal window opened
+    pub withdraw_window_liquidity: u64,
+    /// Amount withdrawn in the current window
+    pub withdraw_window_withdrawn: u64,
+    /// Delay before a queued withdrawal can be executed, in seconds
+    pub withdraw_delay_seconds: i64,
+    /// Whether custom jackpot odds are enabled
+    pub custom_jackpot_odds: bool,
+    /// Custom jackpot odds while the jackpot is empty (ubps)
+    pub custom_jackpot_base_probability_ubps: u64,
+    /// Custom upper bound on jackpot odds (ubps)
+    pub custom_jackpot_max_probability_ubps: u64,
+    /// Custom jackpot edge (BPS)
+    pub custom_jackpot_edge_bps: u64,
 }
 
 impl Pool {
     /// Calculate space needed for Pool account
     pub const SPACE: usize = DISCRIMINATOR_SIZE
-        + PUBKEY_SIZE * 4  // pool_authority, underlying_token_mint, lookup_address, custom_bonus_token_mint, deposit_whitelist_address
-        + BOOL_SIZE * 7    // boolean flags
-        + U64_SIZE * 7     // numeric values
+        + PUBKEY_SIZE * 5  // pool_authority, underlying_token_mint, lookup_address, custom_bonus_token_mint, deposit_whitelist_address
+        + BOOL_SIZE * 12   // boolean flags
+        + U64_SIZE * 16    // numeric values
+        + I64_SIZE * 3     // withdraw_limit_window_seconds, withdraw_window_start, withdraw_delay_seconds
+        + 1;               // bump
+}
+
+/// Queued withdrawal holding LP tokens in escrow until it unlocks
+#[account]
+pub struct WithdrawRequest {
+    /// LP owner who will receive the underlying tokens
+    pub user: Pubkey,
+    /// Pool the LP tokens belong to
+    pub pool: Pubkey,
+    /// LP tokens held in escrow
+    pub lp_amount: u64,
+    /// Timestamp after which the request can be executed, fixed when queued
+    /// so later delay changes don't apply
+    pub unlock_timestamp: i64,
+    /// PDA bump seed
+    pub bump: [u8; 1],
+}
+
+impl WithdrawRequest {
+    /// Calculate space needed for WithdrawRequest account
+    pub const SPACE: usize = DISCRIMINATOR_SIZE
+        + PUBKEY_SIZE * 2  // user, pool
+        + U64_SIZE         // lp_amount
+        + I64_SIZE         // unlock_timestamp
         + 1;               // bump
 }
 
+/// Fee and limit settings in effect for a pool: the global `WhiskyState`
+/// values with any enabled `Pool` overrides applied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffectivePoolConfig {
+    /// Minimum wager amount
+    pub min_wager: u64,
+    /// Protocol fee in basis points
+    pub whisky_fee_bps: u64,
+    /// Pool fee in basis points
+    pub pool_fee_bps: u64,
+    /// Maximum payout percentage (BPS)
+    pub max_payout_bps: u64,
+    /// Maximum house edge allowed (BPS)
+    pub max_house_edge_bps: u64,
+    /// Maximum creator fee allowed (BPS)
+    pub max_creator_fee_bps: u64,
+    /// Maximum jackpot contribution allowed (BPS)
+    pub max_jackpot_fee_bps: u64,
+    /// Maximum combined fees charged on a wager (BPS)
+    pub max_total_fee_bps: u64,
+    /// Anti-spam fee charged per play (zero if the pool is exempt)
+    pub anti_spam_fee: u64,
+    /// Fee kept by the pool on LP withdrawals (BPS)
+    pub pool_withdraw_fee_bps: u64,
+    /// Jackpot odds while the jackpot is empty (ubps)
+    pub jackpot_base_probability_ubps: u64,
+    /// Upper bound on jackpot odds (ubps)
+    pub jackpot_max_probability_ubps: u64,
+    /// Share of a player's fair jackpot expected value withheld (BPS)
+    pub jackpot_edge_bps: u64,
+}
+
+impl EffectivePoolConfig {
+    /// Resolve the settings in effect for `pool`
+    ///
+    /// Pool overrides of the protocol caps can only tighten them, so lowering
+    /// a cap also lowers any override already set above it.
+    pub fn resolve(whisky_state: &WhiskyState, pool: &Pool) -> Self {
+        let pick = |custom: bool, custom_value: u64, default: u64| {
+            if custom { custom_value } else { default }
+        };
+
+        Self {
+            min_wager: pool.min_wager,
+            whisky_fee_bps: pick(pool.custom_whisky_fee, pool.custom_whisky_fee_bps, whisky_state.whisky_fee_bps),
+            pool_fee_bps: pick(pool.custom_pool_fee, pool.custom_pool_fee_bps, whisky_state.default_pool_fee),
+            max_payout_bps: pick(pool.custom_max_payout, pool.custom_max_payout_bps, whisky_state.max_payout_bps)
+                .min(whisky_state.max_payout_bps),
+            max_house_edge_bps: whisky_state.max_house_edge_bps,
+            max_creator_fee_bps: pick(
+                pool.custom_max_creator_fee,
+                pool.custom_max_creator_fee_bps,
+                whisky_state.max_creator_fee_bps,
+            )
+            .min(whisky_state.max_creator_fee_bps),
+            max_jackpot_fee_bps: pick(
+                pool.custom_max_jackpot_fee,
+                pool.custom_max_jackpot_fee_bps,
+                whisky_state.max_jackpot_fee_bps,
+            )
+            .min(whisky_state.max_jackpot_fee_bps),
+            max_total_fee_bps: whisky_state.max_total_fee_bps,
+            anti_spam_fee: pick(pool.anti_spam_fee_exempt, 0, whisky_state.anti_spam_fee),
+            pool_withdraw_fee_bps: pick(
+                pool.custom_pool_withdraw_fee,
+                pool.custom_pool_withdraw_fee_bps,
+                whisky_state.pool_withdraw_fee_bps,
+            ),
+            jackpot_base_probability_ubps: pick(
+                pool.custom_jackpot_odds,
+                pool.custom_jackpot_base_probability_ubps,
+                whisky_state.jackpot_base_probability_ubps,
+            ),
+            jackpot_max_probability_ubps: pick(
+                pool.custom_jackpot_odds,
+                pool.custom_jackpot_max_probability_ubps,
+                whisky_state.jackpot_max_probability_ubps,
+            ),
+            jackpot_edge_bps: pick(
+                pool.custom_jackpot_odds,
+                pool.custom_jackpot_edge_bps,
+                whisky_state.jackpot_edge_bps,
+            ),
+        }
+    }
+}
+
 /// Player account for managing game state
 #[account]
 pub struct Player {
@@ -174,7 +355,7 @@ pub struct Game {
     pub wager: u64,
     /// Underlying tokens used
     pub underlying_used: u64,
-    /// Bonus tokens used
+    /// Bonus tokens burned to cover part of the wager
     pub bonus_used: u64,
     /// Creator fee amount
     pub creator_fee: u64,
@@ -188,7 +369,7 @@ pub struct Game {
     pub jackpot_result: u64,
     /// Jackpot probability in micro basis points
     pub jackpot_probability_ubps: u64,
-    /// Jackpot payout amount
+    /// Total jackpot paid out, before the payout split
     pub jackpot_payout: u64,
     /// Client seed provided by player
     pub client_seed: String,
@@ -204,12 +385,14 @@ pub struct Game {
     pub metadata: String,
     /// PDA bump seed
     pub bump: [u8; 1],
+    /// Lamports held by the game account until it settles or is refunded
+    pub anti_spam_fee: u64,
 }
 
 impl Game {
     /// Calculate space needed for Game account
     pub const SPACE: usize = DISCRIMINATOR_SIZE
-        + U64_SIZE * 12        // numeric fields
+        + U64_SIZE * 13        // numeric fields
         + PUBKEY_SIZE * 4      // pubkey fields
         + 1                    // GameStatus enum
         + VEC_PREFIX_SIZE + (U32_SIZE * MAX_BET_OUTCOMES) // bet vector
@@ -227,21 +410,284 @@ pub enum PoolAction {
     Withdraw,
 }
 
+// Events are emitted through `emit_cpi!` so they survive log truncation.
+// Every event starts with `version` (see `EVENT_VERSION`).
+
+/// Protocol initialization event
+#[event]
+pub struct ProtocolInitialized {
+    pub version: u8,
+    pub authority: Pubkey,
+}
+
+/// Protocol authority change event
+#[event]
+pub struct AuthorityChanged {
+    pub version: u8,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+/// Protocol configuration change event
+#[event]
+pub struct ProtocolConfigUpdated {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub rng_address: Pubkey,
+    pub rng_address_2: Pubkey,
+    pub whisky_fee_bps: u64,
+    pub max_creator_fee_bps: u64,
+    pub pool_creation_fee: u64,
+    pub anti_spam_fee: u64,
+    pub max_house_edge_bps: u64,
+    pub default_pool_fee: u64,
+    pub jackpot_payout_to_user_bps: u64,
+    pub jackpot_payout_to_creator_bps: u64,
+    pub jackpot_payout_to_pool_bps: u64,
+    pub jackpot_payout_to_whisky_bps: u64,
+    pub bonus_to_jackpot_ratio_bps: u64,
+    pub max_payout_bps: u64,
+    pub pool_withdraw_fee_bps: u64,
+    pub pool_creation_allowed: bool,
+    pub pool_deposit_allowed: bool,
+    pub pool_withdraw_allowed: bool,
+    pub playing_allowed: bool,
+    pub distribution_recipient: Pubkey,
+    pub rng_timeout_seconds: i64,
+    pub max_jackpot_fee_bps: u64,
+    pub max_total_fee_bps: u64,
+    pub pool_creation_restricted: bool,
+    pub jackpot_base_probability_ubps: u64,
+    pub jackpot_max_probability_ubps: u64,
+    pub jackpot_edge_bps: u64,
+}
+
+/// Staged RNG key rotation event
+#[event]
+pub struct RngRotationScheduled {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub rng_address: Pubkey,
+    pub pending_rng_address: Pubkey,
+    pub activation_timestamp: i64,
+}
+
+/// Pool creation mint allowlist change event
+#[event]
+pub struct MintApprovalChanged {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub approved: bool,
+}
+
+/// Pool creation event
+#[event]
+pub struct PoolCreated {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub pool_authority: Pubkey,
+    pub token_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub lookup_address: Pubkey,
+    pub creator: Pubkey,
+    pub creation_fee: u64,
+}
+
+/// Bonus token setup event
+#[event]
+pub struct BonusPoolInitialized {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub bonus_mint: Pubkey,
+}
+
+/// Bonus token mint event
+#[event]
+pub struct BonusMinted {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub funder: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub bonus_liquidity: u64,
+}
+
+/// LP token metadata change event
+#[event]
+pub struct LpMetadataUpdated {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub lp_mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
 /// Pool change event
 #[event]
 pub struct PoolChange {
+    pub version: u8,
     pub user: Pubkey,
     pub pool: Pubkey,
     pub token_mint: Pubkey,
     pub action: PoolAction,
     pub amount: u64,
+    pub fee: u64,
     pub post_liquidity: u64,
     pub lp_supply: u64,
 }
 
+/// Queued withdrawal event
+#[event]
+pub struct WithdrawRequested {
+    pub version: u8,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub lp_amount: u64,
+    pub unlock_timestamp: i64,
+}
+
+/// Cancelled withdrawal event
+#[event]
+pub struct WithdrawCancelled {
+    pub version: u8,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub lp_amount: u64,
+}
+
+/// Pool configuration change event
+#[event]
+pub struct PoolConfigUpdated {
+    pub version: u8,
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub min_wager: u64,
+    pub deposit_limit: bool,
+    pub deposit_limit_amount: u64,
+    pub custom_pool_fee: bool,
+    pub custom_pool_fee_bps: u64,
+    pub custom_max_payout: bool,
+    pub custom_max_payout_bps: u64,
+    pub custom_max_creator_fee: bool,
+    pub custom_max_creator_fee_bps: u64,
+    pub custom_max_jackpot_fee: bool,
+    pub custom_max_jackpot_fee_bps: u64,
+    pub custom_pool_withdraw_fee: bool,
+    pub custom_pool_withdraw_fee_bps: u64,
+    pub deposit_whitelist_required: bool,
+    pub deposit_whitelist_address: Pubkey,
+    pub custom_whisky_fee: bool,
+    pub custom_whisky_fee_bps: u64,
+    pub anti_spam_fee_exempt: bool,
+    pub withdraw_limit: bool,
+    pub withdraw_limit_bps: u64,
+    pub withdraw_limit_window_seconds: i64,
+    pub withdraw_delay_seconds: i64,
+    pub custom_jackpot_odds: bool,
+    pub custom_jackpot_base_probability_ubps: u64,
+    pub custom_jackpot_max_probability_ubps: u64,
+    pub custom_jackpot_edge_bps: u64,
+}
+
+/// Player initialization event
+#[event]
+pub struct PlayerInitialized {
+    pub version: u8,
+    pub user: Pubkey,
+    pub player: Pubkey,
+    pub game: Pubkey,
+}
+
+/// Player account close event
+#[event]
+pub struct PlayerClosed {
+    pub version: u8,
+    pub user: Pubkey,
+}
+
+/// Game start event
+#[event]
+pub struct GameStarted {
+    pub version: u8,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub creator: Pubkey,
+    pub nonce: u64,
+    pub wager: u64,
+    pub bonus_used: u64,
+    pub creator_fee: u64,
+    pub whisky_fee: u64,
+    pub pool_fee: u64,
+    pub jackpot_fee: u64,
+    pub anti_spam_fee: u64,
+    pub jackpot_probability_ubps: u64,
+    pub client_seed: String,
+    pub bet: Vec<u32>,
+    pub rng_seed_hashed: String,
+    pub metadata: String,
+}
+
+/// Winnings claim event
+#[event]
+pub struct WinningsClaimed {
+    pub version: u8,
+    pub user: Pubkey,
+    pub token_mint: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+}
+
+/// RNG seed commitment event
+#[event]
+pub struct RngSeedHashProvided {
+    pub version: u8,
+    pub user: Pubkey,
+    pub rng: Pubkey,
+    pub next_rng_seed_hashed: String,
+}
+
+/// Protocol fee distribution event
+#[event]
+pub struct FeesDistributed {
+    pub version: u8,
+    pub token_mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Game refund event
+#[event]
+pub struct GameRefunded {
+    pub version: u8,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub token_mint: Pubkey,
+    pub nonce: u64,
+    pub wager: u64,
+    pub refunded: u64,
+    pub bonus_refunded: u64,
+    pub timestamp: i64,
+}
+
+/// Abandoned game anti-spam fee forfeit event
+#[event]
+pub struct AntiSpamFeeForfeited {
+    pub version: u8,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub nonce: u64,
+    pub rng: Pubkey,
+    pub amount: u64,
+}
+
 /// Game settlement event
 #[event]
 pub struct GameSettled {
+    pub version: u8,
     pub user: Pubkey,
     pub pool: Pubkey,
     pub token_mint: Pubkey,
@@ -264,6 +710,9 @@ pub struct GameSettled {
     pub result_index: u32,
     pub bet: Vec<u32>,
     pub jackpot_payout_to_user: u64,
+    pub jackpot_payout_to_creator: u64,
+    pub jackpot_payout_to_pool: u64,
+    pub jackpot_payout_to_whisky: u64,
     pub pool_liquidity: u64,
     pub rng_seed: String,
     pub next_rng_seed_hashed: String,
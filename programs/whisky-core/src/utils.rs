@@ -1,7 +1,22 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{
+    close_account,
+    spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+    },
+    sync_native, transfer_checked, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface,
+    TransferChecked,
+};
 use sha2::{Digest, Sha256};
 use crate::constants::*;
 use crate::errors::*;
+use crate::state::*;
 
 /// Calculate LP tokens to mint for a deposit
 pub fn calculate_lp_tokens(deposit_amount: u64, pool_liquidity: u64, lp_supply: u64) -> u64 {
@@ -18,6 +33,30 @@ pub fn calculate_lp_tokens(deposit_amount: u64, pool_liquidity: u64, lp_supply:
         .unwrap() as u64
 }
 
+/// Calculate LP tokens for a deposit as `(user_lp, locked_lp)`
+///
+/// The first deposit locks `MINIMUM_LIQUIDITY` LP tokens in the pool so a
+/// donation to the pool's token account can't push the share price high
+/// enough to round later deposits down to nothing. Deposits that would mint
+/// no LP tokens are rejected, as are deposits into a pool whose LP tokens
+/// are backed by nothing, where the existing holders would take a share of
+/// the deposit. `deposit_amount` is what the pool actually received, net of
+/// any Token-2022 transfer fee.
+pub fn calculate_deposit_lp_tokens(
+    deposit_amount: u64,
+    pool_liquidity: u64,
+    lp_supply: u64,
+) -> Result<(u64, u64)> {
+    require!(lp_supply == 0 || pool_liquidity > 0, PoolError::InsufficientLiquidity);
+    let lp_tokens = calculate_lp_tokens(deposit_amount, pool_liquidity, lp_supply);
+    let locked_lp = if lp_supply == 0 { MINIMUM_LIQUIDITY } else { 0 };
+    let user_lp = lp_tokens
+        .checked_sub(locked_lp)
+        .filter(|&user_lp| user_lp > 0)
+        .ok_or(PoolError::InvalidLPTokenCalculation)?;
+    Ok((user_lp, locked_lp))
+}
+
 /// Calculate underlying tokens to withdraw for burning LP tokens
 pub fn calculate_withdraw_amount(lp_tokens: u64, pool_liquidity: u64, lp_supply: u64) -> u64 {
     if lp_supply == 0 {
@@ -32,6 +71,24 @@ pub fn calculate_withdraw_amount(lp_tokens: u64, pool_liquidity: u64, lp_supply:
         .unwrap() as u64
 }
 
+/// Calculate the underlying paid out and the fee kept by the pool for
+/// burning LP tokens
+///
+/// Priced against unreserved liquidity, so exiting ahead of a large win
+/// doesn't dodge the loss. The fee stays in the pool for the remaining LPs.
+pub fn calculate_withdrawal(
+    lp_tokens: u64,
+    pool_liquidity: u64,
+    reserved_liquidity: u64,
+    lp_supply: u64,
+    withdraw_fee_bps: u64,
+) -> (u64, u64) {
+    let available_liquidity = calculate_available_liquidity(pool_liquidity, reserved_liquidity);
+    let gross_amount = calculate_withdraw_amount(lp_tokens, available_liquidity, lp_supply);
+    let withdraw_fee = calculate_fee(gross_amount, withdraw_fee_bps);
+    (gross_amount - withdraw_fee, withdraw_fee)
+}
+
 /// Calculate fee amount from basis points
 pub fn calculate_fee(amount: u64, fee_bps: u64) -> u64 {
     (amount as u128)
@@ -41,30 +98,48 @@ pub fn calculate_fee(amount: u64, fee_bps: u64) -> u64 {
         .unwrap() as u64
 }
 
-/// Calculate jackpot probability based on wager and pool size
-pub fn calculate_jackpot_probability(wager: u64, pool_liquidity: u64) -> u64 {
-    // Base probability scaled by wager size relative to pool
-    let wager_ratio = if pool_liquidity > 0 {
-        (wager as u128)
-            .checked_mul(BPS_PER_WHOLE as u128)
-            .unwrap()
-            .checked_div(pool_liquidity as u128)
-            .unwrap()
-    } else {
-        0
-    };
-    
-    // Jackpot probability in micro basis points (1 ubps = 0.0001 bps)
-    let base_probability = JACKPOT_BASE_PROBABILITY; // 0.0001%
-    
-    // Scale probability by wager ratio (bigger wagers get better jackpot odds)
-    let scaled_probability = base_probability
-        .checked_mul(wager_ratio as u64)
-        .unwrap_or(base_probability)
-        .max(base_probability);
-    
-    // Cap at 1% (100 basis points = 1,000,000 ubps)
-    scaled_probability.min(1_000_000)
+/// Calculate the total held in escrow for a game: the wager plus every fee
+/// charged on top of it
+///
+/// Escrowing the fees lets a refund return everything; settlement routes
+/// them to the creator, protocol, pool and jackpot.
+pub fn calculate_escrow_amount(wager: u64, fees: &[u64]) -> Result<u64> {
+    fees.iter().try_fold(wager, |total, &fee| {
+        total.checked_add(fee).ok_or_else(|| WhiskyError::MathOverflow.into())
+    })
+}
+
+/// Calculate the odds (ubps) of a game winning the jackpot
+///
+/// Odds are priced so the player's expected jackpot winnings equal their
+/// contribution less the jackpot edge:
+///
+/// `odds * (jackpot + contribution) * user_share = contribution * (1 - edge)`
+///
+/// The pot is the pool's jackpot vault, never its LP liquidity, and includes
+/// the player's own contribution as it will at settlement.
+/// An empty jackpot plays at the base odds, and odds never exceed the
+/// configured maximum, where players get less than the fair value.
+pub fn calculate_jackpot_probability(
+    jackpot_fee: u64,
+    jackpot_vault_balance: u64,
+    jackpot_payout_to_user_bps: u64,
+    config: &EffectivePoolConfig,
+) -> u64 {
+    if jackpot_fee == 0 {
+        return 0;
+    }
+    if jackpot_vault_balance == 0 || jackpot_payout_to_user_bps == 0 {
+        return config.jackpot_base_probability_ubps.min(config.jackpot_max_probability_ubps);
+    }
+
+    let pot = jackpot_vault_balance as u128 + jackpot_fee as u128;
+    let numerator = (jackpot_fee as u128)
+        * (BPS_PER_WHOLE.saturating_sub(config.jackpot_edge_bps) as u128)
+        * (UBPS_PER_WHOLE as u128);
+    let denominator = pot * jackpot_payout_to_user_bps as u128;
+
+    (numerator / denominator).min(config.jackpot_max_probability_ubps as u128) as u64
 }
 
 /// Validate bet configuration
@@ -99,16 +174,272 @@ pub fn validate_house_edge(bet: &[u32], max_house_edge_bps: u64) -> Result<()> {
         .unwrap_or(0);
     
     // House edge = (1 - (1 / max_multiplier)) * 100%
-    let house_edge_bps = if max_multiplier > 0 {
-        BPS_PER_WHOLE - (BPS_PER_WHOLE * BPS_PER_WHOLE) / max_multiplier
-    } else {
-        BPS_PER_WHOLE // 100% house edge if no valid outcomes
-    };
+    let house_edge_bps = (BPS_PER_WHOLE * BPS_PER_WHOLE)
+        .checked_div(max_multiplier)
+        .map(|inverse| BPS_PER_WHOLE - inverse)
+        .unwrap_or(BPS_PER_WHOLE); // 100% house edge if no valid outcomes
     
     require!(house_edge_bps <= max_house_edge_bps, GameError::InvalidHouseEdge);
     Ok(())
 }
 
+/// Validate the fees charged on a wager, given as
+/// `[creator, jackpot, protocol, pool]` in basis points
+pub fn validate_fees(
+    fee_bps: &[u64; 4],
+    max_creator_fee_bps: u64,
+    max_jackpot_fee_bps: u64,
+    max_total_fee_bps: u64,
+) -> Result<()> {
+    let [creator_fee_bps, jackpot_fee_bps, _, _] = *fee_bps;
+    require!(creator_fee_bps <= max_creator_fee_bps, GameError::CreatorFeeTooHigh);
+    require!(jackpot_fee_bps <= max_jackpot_fee_bps, GameError::JackpotFeeTooHigh);
+
+    let total_fee_bps = fee_bps
+        .iter()
+        .try_fold(0u64, |total, &fee| total.checked_add(fee))
+        .ok_or(WhiskyError::MathOverflow)?;
+    require!(total_fee_bps <= max_total_fee_bps, GameError::TotalFeeTooHigh);
+
+    Ok(())
+}
+
+/// Calculate the lamport fee for creating a pool, waived for the protocol
+/// authority
+pub fn calculate_pool_creation_fee(whisky_state: &WhiskyState, creator: &Pubkey) -> u64 {
+    if *creator == whisky_state.authority {
+        0
+    } else {
+        whisky_state.pool_creation_fee
+    }
+}
+
+/// Validate that a pool may be created for a mint: creation must be open, and
+/// while restricted, non-authority creators need the mint to be approved
+pub fn validate_pool_creation(
+    whisky_state: &WhiskyState,
+    creator: &Pubkey,
+    mint_approved: bool,
+) -> Result<()> {
+    require!(whisky_state.pool_creation_allowed, WhiskyStateError::PoolCreationNotAllowed);
+    if whisky_state.pool_creation_restricted && *creator != whisky_state.authority {
+        require!(mint_approved, PoolError::MintNotApproved);
+    }
+    Ok(())
+}
+
+/// Validate that the jackpot payout shares, given as
+/// `[user, creator, pool, protocol]` in basis points, add up to 100%
+pub fn validate_jackpot_split(split_bps: &[u64; 4]) -> Result<()> {
+    let total_bps = split_bps
+        .iter()
+        .try_fold(0u64, |total, &share| total.checked_add(share))
+        .ok_or(WhiskyError::MathOverflow)?;
+    require!(total_bps == BPS_PER_WHOLE, GameError::InvalidJackpotConfiguration);
+    Ok(())
+}
+
+/// Split a won jackpot into `[user, creator, pool, protocol]` amounts; the
+/// user's share takes the rounding remainder
+pub fn calculate_jackpot_split(jackpot: u64, split_bps: &[u64; 4]) -> Result<[u64; 4]> {
+    let [_, creator_bps, pool_bps, whisky_bps] = *split_bps;
+    let creator = calculate_fee(jackpot, creator_bps);
+    let pool = calculate_fee(jackpot, pool_bps);
+    let whisky = calculate_fee(jackpot, whisky_bps);
+    let user = jackpot
+        .checked_sub(creator)
+        .and_then(|rest| rest.checked_sub(pool))
+        .and_then(|rest| rest.checked_sub(whisky))
+        .ok_or(GameError::InvalidJackpotConfiguration)?;
+    Ok([user, creator, pool, whisky])
+}
+
+/// Validate jackpot odds parameters
+pub fn validate_jackpot_odds(
+    base_probability_ubps: u64,
+    max_probability_ubps: u64,
+    edge_bps: u64,
+) -> Result<()> {
+    require!(max_probability_ubps <= UBPS_PER_WHOLE, GameError::InvalidJackpotConfiguration);
+    require!(base_probability_ubps <= max_probability_ubps, GameError::InvalidJackpotConfiguration);
+    require!(edge_bps <= BPS_PER_WHOLE, GameError::InvalidJackpotConfiguration);
+    Ok(())
+}
+
+/// Split a wager into `(bonus_used, underlying_used)`, spending the player's
+/// bonus tokens first; fees are always paid in underlying
+pub fn calculate_bonus_usage(wager: u64, bonus_balance: u64) -> (u64, u64) {
+    let bonus_used = wager.min(bonus_balance);
+    (bonus_used, wager - bonus_used)
+}
+
+/// Route the underlying backing a game's burned bonus tokens at settlement
+///
+/// Returns `[to_jackpot, payout_from_bonus, to_pool]`: the
+/// `bonus_to_jackpot_ratio_bps` share feeds the jackpot, the rest pays the
+/// winnings first and whatever is left joins the pool.
+pub fn calculate_bonus_settlement(
+    bonus_used: u64,
+    payout: u64,
+    bonus_to_jackpot_ratio_bps: u64,
+) -> Result<[u64; 3]> {
+    let to_jackpot = calculate_fee(bonus_used, bonus_to_jackpot_ratio_bps);
+    let available = bonus_used
+        .checked_sub(to_jackpot)
+        .ok_or(WhiskyStateError::ConfigurationOutOfBounds)?;
+    let payout_from_bonus = payout.min(available);
+    Ok([to_jackpot, payout_from_bonus, available - payout_from_bonus])
+}
+
+/// Validate pool overrides against the global protocol bounds
+pub fn validate_pool_config(whisky_state: &WhiskyState, pool: &Pool) -> Result<()> {
+    require!(pool.min_wager >= MIN_WAGER, WhiskyStateError::ConfigurationOutOfBounds);
+    require!(
+        !pool.deposit_limit || pool.deposit_limit_amount > 0,
+        WhiskyStateError::ConfigurationOutOfBounds
+    );
+    require!(
+        !pool.deposit_whitelist_required || pool.deposit_whitelist_address != Pubkey::default(),
+        WhiskyStateError::InvalidParameter
+    );
+
+    if pool.custom_max_payout {
+        require!(
+            pool.custom_max_payout_bps > 0 && pool.custom_max_payout_bps <= whisky_state.max_payout_bps,
+            PoolError::CustomFeeOutOfBounds
+        );
+    }
+    if pool.custom_max_creator_fee {
+        require!(
+            pool.custom_max_creator_fee_bps <= whisky_state.max_creator_fee_bps,
+            PoolError::CustomFeeOutOfBounds
+        );
+    }
+    if pool.custom_max_jackpot_fee {
+        require!(
+            pool.custom_max_jackpot_fee_bps <= whisky_state.max_jackpot_fee_bps,
+            PoolError::CustomFeeOutOfBounds
+        );
+    }
+    // Pools may waive or reduce the withdrawal fee, never raise it
+    if pool.custom_pool_withdraw_fee {
+        require!(
+            pool.custom_pool_withdraw_fee_bps <= whisky_state.pool_withdraw_fee_bps,
+            PoolError::CustomFeeOutOfBounds
+        );
+    }
+
+    if pool.custom_jackpot_odds {
+        validate_jackpot_odds(
+            pool.custom_jackpot_base_probability_ubps,
+            pool.custom_jackpot_max_probability_ubps,
+            pool.custom_jackpot_edge_bps,
+        )?;
+        require!(
+            pool.custom_jackpot_max_probability_ubps <= whisky_state.jackpot_max_probability_ubps,
+            PoolError::CustomFeeOutOfBounds
+        );
+    }
+
+    if pool.withdraw_limit {
+        require!(
+            (MIN_WITHDRAW_LIMIT_BPS..=BPS_PER_WHOLE).contains(&pool.withdraw_limit_bps),
+            WhiskyStateError::ConfigurationOutOfBounds
+        );
+        require!(
+            (1..=MAX_WITHDRAW_LIMIT_WINDOW_SECONDS).contains(&pool.withdraw_limit_window_seconds),
+            WhiskyStateError::ConfigurationOutOfBounds
+        );
+    }
+    require!(
+        (0..=MAX_WITHDRAW_DELAY_SECONDS).contains(&pool.withdraw_delay_seconds),
+        WhiskyStateError::ConfigurationOutOfBounds
+    );
+
+    // The fees every play pays must leave room under the total fee ceiling
+    let config = EffectivePoolConfig::resolve(whisky_state, pool);
+    let base_fee_bps = config
+        .whisky_fee_bps
+        .checked_add(config.pool_fee_bps)
+        .ok_or(WhiskyError::MathOverflow)?;
+    require!(base_fee_bps <= config.max_total_fee_bps, PoolError::CustomFeeOutOfBounds);
+
+    Ok(())
+}
+
+/// Validate that a deposit keeps pool liquidity within its deposit limit
+pub fn validate_deposit_limit(pool: &Pool, pool_liquidity: u64, amount: u64) -> Result<()> {
+    if !pool.deposit_limit {
+        return Ok(());
+    }
+
+    let post_liquidity = pool_liquidity
+        .checked_add(amount)
+        .ok_or(WhiskyError::MathOverflow)?;
+    require!(post_liquidity <= pool.deposit_limit_amount, PoolError::DepositLimitExceeded);
+    Ok(())
+}
+
+/// Count a withdrawal against the pool's window cap, if enabled
+///
+/// A window opens on the first withdrawal after the previous one expired and
+/// snapshots pool liquidity; at most `withdraw_limit_bps` of that snapshot may
+/// leave before the window closes.
+pub fn record_withdrawal(pool: &mut Pool, pool_liquidity: u64, amount: u64, now: i64) -> Result<()> {
+    if !pool.withdraw_limit {
+        return Ok(());
+    }
+
+    let window_end = pool.withdraw_window_start.saturating_add(pool.withdraw_limit_window_seconds);
+    if now >= window_end {
+        pool.withdraw_window_start = now;
+        pool.withdraw_window_liquidity = pool_liquidity;
+        pool.withdraw_window_withdrawn = 0;
+    }
+
+    let cap = calculate_fee(pool.withdraw_window_liquidity, pool.withdraw_limit_bps);
+    let withdrawn = pool
+        .withdraw_window_withdrawn
+        .checked_add(amount)
+        .ok_or(WhiskyError::MathOverflow)?;
+    require!(withdrawn <= cap, PoolError::WithdrawalLimitExceeded);
+
+    pool.withdraw_window_withdrawn = withdrawn;
+    Ok(())
+}
+
+/// Validate that the depositor holds the pool's whitelist token, if required
+pub fn validate_deposit_whitelist(
+    pool: &Pool,
+    user: &Pubkey,
+    whitelist_token_account: Option<&spl_token_2022::state::Account>,
+) -> Result<()> {
+    if !pool.deposit_whitelist_required {
+        return Ok(());
+    }
+
+    let holding = whitelist_token_account.ok_or(PoolError::WhitelistCheckFailed)?;
+    require!(holding.mint == pool.deposit_whitelist_address, PoolError::WhitelistCheckFailed);
+    require!(holding.owner == *user, PoolError::WhitelistCheckFailed);
+    require!(holding.amount > 0, PoolError::WhitelistCheckFailed);
+    Ok(())
+}
+
+/// Calculate the largest payout a bet can win, reserved in the pool until
+/// the game settles or is refunded
+pub fn calculate_max_payout(bet: &[u32], wager: u64) -> Result<u64> {
+    let max_multiplier = (0..bet.len())
+        .map(|index| calculate_multiplier(bet, index))
+        .max()
+        .unwrap_or(0);
+    calculate_payout(wager, max_multiplier)
+}
+
+/// Liquidity not reserved for the maximum payout of unsettled games
+pub fn calculate_available_liquidity(pool_liquidity: u64, reserved_liquidity: u64) -> u64 {
+    pool_liquidity.saturating_sub(reserved_liquidity)
+}
+
 /// Validate maximum payout doesn't exceed pool limits
 pub fn validate_max_payout(
     bet: &[u32], 
@@ -116,20 +447,7 @@ pub fn validate_max_payout(
     pool_liquidity: u64, 
     max_payout_bps: u64
 ) -> Result<()> {
-    let total_weight: u64 = bet.iter().map(|&x| x as u64).sum();
-    
-    // Calculate maximum possible payout
-    let max_multiplier = bet.iter()
-        .filter(|&&weight| weight > 0)
-        .map(|&weight| (total_weight * BPS_PER_WHOLE) / (weight as u64))
-        .max()
-        .unwrap_or(0);
-    
-    let max_payout = (wager as u128)
-        .checked_mul(max_multiplier as u128)
-        .unwrap()
-        .checked_div(BPS_PER_WHOLE as u128)
-        .unwrap() as u64;
+    let max_payout = calculate_max_payout(bet, wager)?;
     
     // Check against pool liquidity limit
     let pool_limit = calculate_fee(pool_liquidity, max_payout_bps);
@@ -147,6 +465,109 @@ pub fn get_game_hash(rng_seed: &str, client_seed: &str, nonce: u64) -> [u8; 32]
     hasher.finalize().into()
 }
 
+/// Hash an RNG seed into its hex-encoded SHA-256 commitment
+pub fn hash_rng_seed(rng_seed: &str) -> String {
+    let hash: [u8; 32] = Sha256::digest(rng_seed.as_bytes()).into();
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Validate that a seed commitment is a hex-encoded SHA-256 digest
+pub fn validate_seed_hash(seed_hash: &str) -> Result<()> {
+    require!(seed_hash.len() == SEED_HASH_LENGTH, RngError::InvalidSeedHash);
+    require!(
+        seed_hash.bytes().all(|byte| byte.is_ascii_hexdigit()),
+        RngError::InvalidSeedHash
+    );
+    Ok(())
+}
+
+/// Verify a revealed RNG seed against the commitment made before play
+pub fn verify_rng_seed(rng_seed: &str, committed_hash: &str) -> Result<()> {
+    require!(!committed_hash.is_empty(), RngError::SeedHashNotCommitted);
+    require!(!rng_seed.is_empty(), RngError::InvalidRngSeed);
+    require!(rng_seed.len() <= MAX_STRING_LENGTH, RngError::InvalidRngSeed);
+    require!(
+        hash_rng_seed(rng_seed).eq_ignore_ascii_case(committed_hash),
+        RngError::SeedHashMismatch
+    );
+    Ok(())
+}
+
+/// RNG keys accepted at `now` as `[primary, failover]`
+///
+/// A staged key counts as the primary from its activation time, with the key
+/// it replaces as the failover, even before the rotation is written back.
+pub fn effective_rng_addresses(whisky_state: &WhiskyState, now: i64) -> [Pubkey; 2] {
+    if whisky_state.pending_rng_address != Pubkey::default()
+        && now >= whisky_state.pending_rng_activation_timestamp
+    {
+        [whisky_state.pending_rng_address, whisky_state.rng_address]
+    } else {
+        [whisky_state.rng_address, whisky_state.rng_address_2]
+    }
+}
+
+/// Check whether `rng` may settle games and commit seed hashes
+pub fn is_rng_authority(whisky_state: &WhiskyState, rng: &Pubkey, now: i64) -> bool {
+    *rng != Pubkey::default() && effective_rng_addresses(whisky_state, now).contains(rng)
+}
+
+/// Write back a staged RNG key rotation once it is active; runs on the next
+/// config or staging call
+pub fn apply_rng_rotation(whisky_state: &mut WhiskyState, now: i64) {
+    [whisky_state.rng_address, whisky_state.rng_address_2] = effective_rng_addresses(whisky_state, now);
+    if now >= whisky_state.pending_rng_activation_timestamp {
+        whisky_state.pending_rng_address = Pubkey::default();
+        whisky_state.pending_rng_activation_timestamp = 0;
+    }
+}
+
+/// Unlock time for a withdrawal queued at `now`, fixed when it is queued;
+/// the delay is capped at `MAX_WITHDRAW_DELAY_SECONDS`
+pub fn calculate_unlock_timestamp(now: i64, withdraw_delay_seconds: i64) -> Result<i64> {
+    now.checked_add(withdraw_delay_seconds.clamp(0, MAX_WITHDRAW_DELAY_SECONDS))
+        .ok_or(WhiskyError::MathOverflow.into())
+}
+
+/// Validate a direct RNG key edit; while a rotation is staged the keys can't
+/// change, or the rotation would demote the wrong key
+pub fn validate_rng_config_update(
+    whisky_state: &WhiskyState,
+    rng_address: &Pubkey,
+    rng_address_2: &Pubkey,
+) -> Result<()> {
+    require!(
+        whisky_state.pending_rng_address == Pubkey::default()
+            || (*rng_address == whisky_state.rng_address && *rng_address_2 == whisky_state.rng_address_2),
+        WhiskyStateError::InvalidParameter
+    );
+    Ok(())
+}
+
+/// Check whether a game was left unrefunded long after the RNG timed out,
+/// so its anti-spam fee can be forfeited
+pub fn is_game_abandoned(game_timestamp: i64, timeout_seconds: i64, now: i64) -> bool {
+    match timeout_seconds.checked_add(ANTI_SPAM_FORFEIT_DELAY_SECONDS) {
+        Some(abandon_after) => is_rng_timed_out(game_timestamp, abandon_after, now),
+        None => false,
+    }
+}
+
+/// Validate that escrowed winnings can be claimed: anything but a game
+/// still waiting on the RNG, including after a refund
+pub fn validate_claim(status: &GameStatus) -> Result<()> {
+    require!(*status != GameStatus::ResultRequested, PlayerError::GameInProgress);
+    Ok(())
+}
+
+/// Check whether the RNG has missed its settlement window for a game
+pub fn is_rng_timed_out(game_timestamp: i64, timeout_seconds: i64, now: i64) -> bool {
+    match game_timestamp.checked_add(timeout_seconds) {
+        Some(deadline) => now >= deadline,
+        None => false,
+    }
+}
+
 /// Calculate game result from RNG
 pub fn calculate_game_result(
     rng_seed: &str, 
@@ -178,13 +599,13 @@ pub fn calculate_game_result(
 
 /// Calculate jackpot result
 pub fn calculate_jackpot_result(game_hash: &[u8; 32], jackpot_probability_ubps: u64) -> bool {
-    // Use bytes 4-7 for jackpot calculation
-    let jackpot_random = u32::from_le_bytes([
-        game_hash[4], game_hash[5], game_hash[6], game_hash[7]
-    ]);
+    // Use bytes 4-11 for jackpot calculation
+    let mut jackpot_bytes = [0u8; 8];
+    jackpot_bytes.copy_from_slice(&game_hash[4..12]);
+    let jackpot_random = u64::from_le_bytes(jackpot_bytes);
     
-    // Convert to range 0-999,999 (1 million outcomes)
-    let jackpot_outcome = (jackpot_random as u64) % 1_000_000;
+    // Convert to range 0-99,999,999 (one outcome per ubps)
+    let jackpot_outcome = jackpot_random % UBPS_PER_WHOLE;
     
     // Win if outcome is less than probability
     jackpot_outcome < jackpot_probability_ubps
@@ -205,6 +626,15 @@ pub fn calculate_multiplier(bet: &[u32], outcome_index: usize) -> u64 {
     (total_weight * BPS_PER_WHOLE) / outcome_weight
 }
 
+/// Calculate payout for a wager at the given multiplier (BPS)
+pub fn calculate_payout(wager: u64, multiplier_bps: u64) -> Result<u64> {
+    let payout = (wager as u128)
+        .checked_mul(multiplier_bps as u128)
+        .ok_or(WhiskyError::MathOverflow)?
+        / BPS_PER_WHOLE as u128;
+    u64::try_from(payout).map_err(|_| WhiskyError::MathOverflow.into())
+}
+
 /// Calculate expected return for a bet
 pub fn calculate_expected_return(bet: &[u32]) -> u64 {
     let total_weight: u64 = bet.iter().map(|&x| x as u64).sum();
@@ -227,6 +657,178 @@ pub fn calculate_expected_return(bet: &[u32]) -> u64 {
     expected_return
 }
 
+/// Transfer tokens out of a PDA-owned token account, skipping zero amounts
+pub fn transfer_from_pda<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked { from, mint: mint.to_account_info(), to, authority },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Token-2022 extensions a pool's underlying mint must not have: each lets
+/// someone other than the pool move, freeze or gate its funds
+pub const UNSUPPORTED_MINT_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::PermanentDelegate,
+    ExtensionType::NonTransferable,
+    ExtensionType::TransferHook,
+    ExtensionType::DefaultAccountState,
+];
+
+/// Extensions enabled on a mint; always empty for legacy SPL Token mints
+pub fn mint_extension_types(mint: &AccountInfo) -> Result<Vec<ExtensionType>> {
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    Ok(mint_state.get_extension_types()?)
+}
+
+/// Validate that a mint has no extensions that break pool invariants
+pub fn validate_mint_extensions(extensions: &[ExtensionType]) -> Result<()> {
+    require!(
+        !extensions.iter().any(|extension| UNSUPPORTED_MINT_EXTENSIONS.contains(extension)),
+        PoolError::UnsupportedMintExtension
+    );
+    Ok(())
+}
+
+/// Transfer fee a mint charges in the current epoch, if it has one
+pub fn current_transfer_fee(mint: &AccountInfo) -> Result<Option<TransferFee>> {
+    let data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(None);
+    };
+    Ok(Some(*config.get_epoch_fee(Clock::get()?.epoch)))
+}
+
+/// Amount to send so that `net_amount` arrives after the transfer fee
+pub fn calculate_gross_transfer_amount(transfer_fee: Option<&TransferFee>, net_amount: u64) -> Result<u64> {
+    let Some(transfer_fee) = transfer_fee else {
+        return Ok(net_amount);
+    };
+    transfer_fee
+        .calculate_pre_fee_amount(net_amount)
+        .ok_or_else(|| WhiskyError::MathOverflow.into())
+}
+
+/// Amount that arrives when sending `amount` after the transfer fee
+pub fn calculate_net_transfer_amount(transfer_fee: Option<&TransferFee>, amount: u64) -> Result<u64> {
+    let Some(transfer_fee) = transfer_fee else {
+        return Ok(amount);
+    };
+    transfer_fee
+        .calculate_post_fee_amount(amount)
+        .ok_or_else(|| WhiskyError::MathOverflow.into())
+}
+
+/// Validate that native SOL handling is only requested for wrapped SOL
+pub fn validate_native_sol(native_sol: bool, mint: &Pubkey) -> Result<()> {
+    require!(
+        !native_sol || *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID,
+        WhiskyError::InvalidMint
+    );
+    Ok(())
+}
+
+/// Token account a user pays from or is paid into: the temporary WSOL
+/// account for native SOL, otherwise their underlying ATA
+pub fn user_token_account<'info>(
+    native_sol: bool,
+    user_underlying_ata: &Option<InterfaceAccount<'info, TokenAccount>>,
+    wsol_temp_account: &Option<InterfaceAccount<'info, TokenAccount>>,
+) -> Result<AccountInfo<'info>> {
+    let account = if native_sol { wsol_temp_account } else { user_underlying_ata };
+    Ok(account.as_ref().ok_or(WhiskyError::InvalidAccount)?.to_account_info())
+}
+
+/// Wrap lamports from `from` into a WSOL token account
+pub fn wrap_sol<'info>(
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    wsol_account: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer { from, to: wsol_account.clone() },
+        ),
+        amount,
+    )?;
+    sync_native(CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative { account: wsol_account },
+    ))
+}
+
+/// Unwrap a WSOL token account by closing it, sending its balance and rent
+/// to `destination`
+pub fn unwrap_sol<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    wsol_account: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount { account: wsol_account, destination, authority },
+        signer_seeds,
+    ))
+}
+
+/// Symbol used to label an underlying token: its metadata symbol, or a short
+/// prefix of the mint address when it has none
+pub fn underlying_display_symbol(metadata_symbol: Option<&str>, mint: &Pubkey) -> String {
+    // Older metadata accounts pad strings with null bytes
+    let symbol = metadata_symbol
+        .map(|symbol| symbol.trim_matches(char::from(0)).trim())
+        .unwrap_or_default();
+
+    if symbol.is_empty() {
+        mint.to_string().chars().take(LP_TOKEN_FALLBACK_SYMBOL_LENGTH).collect()
+    } else {
+        symbol.to_string()
+    }
+}
+
+/// Name for a pool's LP token, e.g. "Whisky USDC LP"
+pub fn lp_token_name(underlying_symbol: &str) -> String {
+    let max_symbol_length = mpl_token_metadata::MAX_NAME_LENGTH
+        - LP_TOKEN_NAME_PREFIX.len()
+        - LP_TOKEN_NAME_SUFFIX.len();
+    let symbol: String = underlying_symbol.chars().take(max_symbol_length).collect();
+    format!("{}{}{}", LP_TOKEN_NAME_PREFIX, symbol, LP_TOKEN_NAME_SUFFIX)
+}
+
+/// Symbol for a pool's LP token, e.g. "USDC-LP"
+pub fn lp_token_symbol(underlying_symbol: &str) -> String {
+    let max_symbol_length = mpl_token_metadata::MAX_SYMBOL_LENGTH - LP_TOKEN_SYMBOL_SUFFIX.len();
+    let symbol: String = underlying_symbol.chars().take(max_symbol_length).collect();
+    format!("{}{}", symbol, LP_TOKEN_SYMBOL_SUFFIX)
+}
+
 /// Validate string length
 pub fn validate_string_length(s: &str, max_length: usize) -> Result<()> {
     require!(s.len() <= max_length, GameError::InvalidMetadata);
@@ -254,6 +856,32 @@ pub fn generate_random_number(seed: &[u8], min: u64, max: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
+
+    fn test_whisky_state() -> WhiskyState {
+        WhiskyState {
+            anti_spam_fee: DEFAULT_ANTI_SPAM_FEE,
+            whisky_fee_bps: DEFAULT_WHISKY_FEE_BPS,
+            default_pool_fee: DEFAULT_POOL_FEE_BPS,
+            max_house_edge_bps: DEFAULT_MAX_HOUSE_EDGE_BPS,
+            max_creator_fee_bps: DEFAULT_MAX_CREATOR_FEE_BPS,
+            max_payout_bps: DEFAULT_MAX_PAYOUT_BPS,
+            max_jackpot_fee_bps: DEFAULT_MAX_JACKPOT_FEE_BPS,
+            max_total_fee_bps: DEFAULT_MAX_TOTAL_FEE_BPS,
+            pool_withdraw_fee_bps: DEFAULT_POOL_WITHDRAW_FEE_BPS,
+            jackpot_payout_to_user_bps: DEFAULT_JACKPOT_PAYOUT_TO_USER_BPS,
+            jackpot_base_probability_ubps: DEFAULT_JACKPOT_BASE_PROBABILITY_UBPS,
+            jackpot_max_probability_ubps: DEFAULT_JACKPOT_MAX_PROBABILITY_UBPS,
+            jackpot_edge_bps: DEFAULT_JACKPOT_EDGE_BPS,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_space_fits_serialized_size() {
+        assert!(WhiskyState::default().try_to_vec().unwrap().len() + DISCRIMINATOR_SIZE <= WhiskyState::SPACE);
+        assert!(Pool::default().try_to_vec().unwrap().len() + DISCRIMINATOR_SIZE <= Pool::SPACE);
+    }
 
     #[test]
     fn test_calculate_lp_tokens() {
@@ -264,6 +892,61 @@ mod tests {
         assert_eq!(calculate_lp_tokens(1000, 10000, 5000), 500);
     }
 
+    #[test]
+    fn test_calculate_deposit_lp_tokens() {
+        assert_eq!(calculate_deposit_lp_tokens(10_000, 0, 0).unwrap(), (9_000, MINIMUM_LIQUIDITY));
+        assert_eq!(calculate_deposit_lp_tokens(1_000, 10_000, 5_000).unwrap(), (500, 0));
+
+        // First deposits must cover the locked shares, later ones must mint something
+        assert!(calculate_deposit_lp_tokens(MINIMUM_LIQUIDITY, 0, 0).is_err());
+        assert!(calculate_deposit_lp_tokens(1, 10_000, 5_000).is_err());
+
+        // A drained pool with LP tokens outstanding can't price a deposit
+        assert!(calculate_deposit_lp_tokens(1_000_000, 0, 5_000).is_err());
+    }
+
+    #[test]
+    fn test_donation_attack_without_locked_shares() {
+        // Attacker mints a single LP token, then donates straight to the pool
+        let attacker_lp = calculate_lp_tokens(1, 0, 0);
+        assert_eq!(attacker_lp, 1);
+        let pool_liquidity = 1 + 1_000_000;
+
+        // The victim's deposit rounds down to zero LP tokens...
+        assert_eq!(calculate_lp_tokens(1_000_000, pool_liquidity, attacker_lp), 0);
+
+        // ...and the attacker's single token now redeems the victim's funds too
+        let drained = calculate_withdraw_amount(attacker_lp, pool_liquidity + 1_000_000, attacker_lp);
+        assert_eq!(drained, 2_000_001);
+    }
+
+    #[test]
+    fn test_donation_attack_with_locked_shares() {
+        // The same attack is rejected outright at its cheapest
+        assert!(calculate_deposit_lp_tokens(1, 0, 0).is_err());
+
+        // The smallest viable first deposit leaves the attacker with one LP token
+        let (attacker_lp, locked_lp) = calculate_deposit_lp_tokens(MINIMUM_LIQUIDITY + 1, 0, 0).unwrap();
+        assert_eq!(attacker_lp, 1);
+        let mut lp_supply = attacker_lp + locked_lp;
+        let mut pool_liquidity = MINIMUM_LIQUIDITY + 1 + 1_000_000;
+
+        // A deposit the size of the donation now mints a fair share
+        let (victim_lp, _) = calculate_deposit_lp_tokens(1_000_000, pool_liquidity, lp_supply).unwrap();
+        assert_eq!(victim_lp, 999);
+        lp_supply += victim_lp;
+        pool_liquidity += 1_000_000;
+
+        // The victim loses under 0.1%, the attacker keeps ~0.1% of the donation
+        let victim_out = calculate_withdraw_amount(victim_lp, pool_liquidity, lp_supply);
+        let attacker_out = calculate_withdraw_amount(attacker_lp, pool_liquidity, lp_supply);
+        assert!(victim_out >= 999_000);
+        assert!(attacker_out < 1_100);
+
+        // Deposits too small to mint an LP token are rejected instead of absorbed
+        assert!(calculate_deposit_lp_tokens(1_000, pool_liquidity, lp_supply).is_err());
+    }
+
     #[test]
     fn test_calculate_withdraw_amount() {
         assert_eq!(calculate_withdraw_amount(500, 10000, 5000), 1000);
@@ -286,6 +969,610 @@ mod tests {
     }
 
     #[test]
+    fn test_calculate_escrow_amount() {
+        assert_eq!(calculate_escrow_amount(1000, &[]).unwrap(), 1000);
+        assert_eq!(calculate_escrow_amount(1000, &[10, 20, 10, 5]).unwrap(), 1045);
+        assert!(calculate_escrow_amount(u64::MAX, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_calculate_payout() {
+        assert_eq!(calculate_payout(1000, 40000).unwrap(), 4000); // 4x
+        assert_eq!(calculate_payout(1000, 0).unwrap(), 0); // Losing outcome
+        assert!(calculate_payout(u64::MAX, 20000).is_err()); // Overflow
+    }
+
+    #[test]
+    fn test_hash_rng_seed() {
+        // sha256("abc")
+        assert_eq!(
+            hash_rng_seed("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(validate_seed_hash(&hash_rng_seed("abc")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_seed_hash() {
+        assert!(validate_seed_hash("").is_err());
+        assert!(validate_seed_hash("abc123").is_err()); // Too short
+        assert!(validate_seed_hash(&"z".repeat(SEED_HASH_LENGTH)).is_err()); // Not hex
+        assert!(validate_seed_hash(&"A".repeat(SEED_HASH_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rng_seed() {
+        let committed = hash_rng_seed("server-seed");
+        assert!(verify_rng_seed("server-seed", &committed).is_ok());
+        assert!(verify_rng_seed("server-seed", &committed.to_uppercase()).is_ok());
+        assert!(verify_rng_seed("other-seed", &committed).is_err());
+        assert!(verify_rng_seed("server-seed", "").is_err()); // Nothing committed
+        assert!(verify_rng_seed("", &hash_rng_seed("")).is_err()); // Empty seed
+    }
+
+    #[test]
+    fn test_is_rng_timed_out() {
+        assert!(!is_rng_timed_out(1_000, 600, 1_599));
+        assert!(is_rng_timed_out(1_000, 600, 1_600));
+        assert!(!is_rng_timed_out(i64::MAX, 600, i64::MAX)); // Deadline overflow
+    }
+
+    #[test]
+    fn test_rng_rotation() {
+        let old_rng = Pubkey::new_unique();
+        let failover = Pubkey::new_unique();
+        let new_rng = Pubkey::new_unique();
+        let mut whisky_state = WhiskyState {
+            rng_address: old_rng,
+            rng_address_2: failover,
+            ..Default::default()
+        };
+
+        // Either configured key can act, an unset one never can
+        assert!(is_rng_authority(&whisky_state, &old_rng, 0));
+        assert!(is_rng_authority(&whisky_state, &failover, 0));
+        assert!(!is_rng_authority(&whisky_state, &new_rng, 0));
+        assert!(!is_rng_authority(&WhiskyState::default(), &Pubkey::default(), 0));
+
+        whisky_state.pending_rng_address = new_rng;
+        whisky_state.pending_rng_activation_timestamp = 1_000;
+
+        // Nothing changes before activation
+        assert!(!is_rng_authority(&whisky_state, &new_rng, 999));
+        apply_rng_rotation(&mut whisky_state, 999);
+        assert_eq!(whisky_state.rng_address, old_rng);
+        assert_eq!(whisky_state.pending_rng_address, new_rng);
+
+        // From activation the new key is live and the old one stays on for
+        // games it committed to, without waiting for the write-back
+        assert!(is_rng_authority(&whisky_state, &new_rng, 1_000));
+        assert!(is_rng_authority(&whisky_state, &old_rng, 1_000));
+        assert!(!is_rng_authority(&whisky_state, &failover, 1_000));
+
+        // Direct key edits wait for the staged rotation
+        assert!(validate_rng_config_update(&whisky_state, &old_rng, &failover).is_ok());
+        assert!(validate_rng_config_update(&whisky_state, &Pubkey::new_unique(), &failover).is_err());
+        assert!(validate_rng_config_update(&whisky_state, &old_rng, &Pubkey::new_unique()).is_err());
+
+        apply_rng_rotation(&mut whisky_state, 1_000);
+        assert!(validate_rng_config_update(&whisky_state, &Pubkey::new_unique(), &failover).is_ok());
+        assert_eq!(whisky_state.rng_address, new_rng);
+        assert_eq!(whisky_state.rng_address_2, old_rng);
+        assert_eq!(whisky_state.pending_rng_address, Pubkey::default());
+        assert!(is_rng_authority(&whisky_state, &new_rng, 2_000));
+        assert!(is_rng_authority(&whisky_state, &old_rng, 2_000));
+    }
+
+    #[test]
+    fn test_claim_after_refund() {
+        assert!(validate_claim(&GameStatus::Ready).is_ok());
+        assert!(validate_claim(&GameStatus::ResultRequested).is_err());
+
+        // game_refund resets the game to None; earlier winnings stay claimable
+        assert!(validate_claim(&GameStatus::None).is_ok());
+    }
+
+    #[test]
+    fn test_is_game_abandoned() {
+        let abandoned_at = 1_600 + ANTI_SPAM_FORFEIT_DELAY_SECONDS;
+        assert!(!is_game_abandoned(1_000, 600, 1_600)); // Refundable, not yet abandoned
+        assert!(!is_game_abandoned(1_000, 600, abandoned_at - 1));
+        assert!(is_game_abandoned(1_000, 600, abandoned_at));
+        assert!(!is_game_abandoned(1_000, i64::MAX, i64::MAX)); // Delay overflow
+    }
+
+    #[test]
+    fn test_validate_fees() {
+        assert!(validate_fees(&[500, 100, 200, 100], 500, 100, 1_000).is_ok());
+        assert!(validate_fees(&[501, 100, 200, 100], 500, 100, 1_000).is_err()); // Creator cap
+        assert!(validate_fees(&[0, 101, 200, 100], 500, 100, 1_000).is_err()); // Jackpot cap
+        assert!(validate_fees(&[500, 100, 300, 200], 500, 100, 1_000).is_err()); // Total cap
+        assert!(validate_fees(&[0, 0, u64::MAX, 1], 0, 0, u64::MAX).is_err()); // Overflow
+    }
+
+    #[test]
+    fn test_effective_pool_config_defaults() {
+        let whisky_state = test_whisky_state();
+        let pool = Pool { min_wager: 1_000_000, ..Default::default() };
+
+        let config = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(config.min_wager, 1_000_000);
+        assert_eq!(config.whisky_fee_bps, DEFAULT_WHISKY_FEE_BPS);
+        assert_eq!(config.pool_fee_bps, DEFAULT_POOL_FEE_BPS);
+        assert_eq!(config.max_payout_bps, DEFAULT_MAX_PAYOUT_BPS);
+        assert_eq!(config.max_creator_fee_bps, DEFAULT_MAX_CREATOR_FEE_BPS);
+        assert_eq!(config.max_jackpot_fee_bps, DEFAULT_MAX_JACKPOT_FEE_BPS);
+        assert_eq!(config.anti_spam_fee, DEFAULT_ANTI_SPAM_FEE);
+        assert_eq!(config.pool_withdraw_fee_bps, DEFAULT_POOL_WITHDRAW_FEE_BPS);
+    }
+
+    #[test]
+    fn test_effective_pool_config_overrides() {
+        let whisky_state = test_whisky_state();
+        let pool = Pool {
+            custom_pool_fee: true,
+            custom_pool_fee_bps: 0,
+            custom_whisky_fee: true,
+            custom_whisky_fee_bps: 50,
+            custom_max_payout: true,
+            custom_max_payout_bps: 500,
+            custom_max_creator_fee: true,
+            custom_max_creator_fee_bps: 100,
+            custom_max_jackpot_fee: true,
+            custom_max_jackpot_fee_bps: 10,
+            anti_spam_fee_exempt: true,
+            custom_pool_withdraw_fee: true,
+            custom_pool_withdraw_fee_bps: 0,
+            ..Default::default()
+        };
+
+        let config = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(config.pool_fee_bps, 0);
+        assert_eq!(config.whisky_fee_bps, 50);
+        assert_eq!(config.max_payout_bps, 500);
+        assert_eq!(config.max_creator_fee_bps, 100);
+        assert_eq!(config.max_jackpot_fee_bps, 10);
+        assert_eq!(config.anti_spam_fee, 0);
+        assert_eq!(config.pool_withdraw_fee_bps, 0);
+
+        // Values are ignored unless their override flag is set
+        let pool = Pool { custom_pool_fee_bps: 0, custom_max_payout_bps: 500, ..Default::default() };
+        let config = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(config.pool_fee_bps, DEFAULT_POOL_FEE_BPS);
+        assert_eq!(config.max_payout_bps, DEFAULT_MAX_PAYOUT_BPS);
+    }
+
+    #[test]
+    fn test_effective_pool_config_clamps_overrides_to_global_caps() {
+        let pool = Pool {
+            custom_max_payout: true,
+            custom_max_payout_bps: 500,
+            custom_max_creator_fee: true,
+            custom_max_creator_fee_bps: 100,
+            custom_max_jackpot_fee: true,
+            custom_max_jackpot_fee_bps: 10,
+            ..Default::default()
+        };
+
+        // The protocol lowers its caps below the pool's existing overrides
+        let whisky_state = WhiskyState {
+            max_payout_bps: 200,
+            max_creator_fee_bps: 40,
+            max_jackpot_fee_bps: 5,
+            ..test_whisky_state()
+        };
+        let config = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(config.max_payout_bps, 200);
+        assert_eq!(config.max_creator_fee_bps, 40);
+        assert_eq!(config.max_jackpot_fee_bps, 5);
+
+        // Overrides below the caps still apply
+        let whisky_state = WhiskyState { max_payout_bps: 1_000, ..test_whisky_state() };
+        let config = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(config.max_payout_bps, 500);
+    }
+
+    /// Expected jackpot winnings for a player, in token units
+    fn jackpot_expected_value(probability_ubps: u64, pot: u64, user_share_bps: u64) -> f64 {
+        probability_ubps as f64 / UBPS_PER_WHOLE as f64 * pot as f64 * user_share_bps as f64
+            / BPS_PER_WHOLE as f64
+    }
+
+    #[test]
+    fn test_jackpot_probability_expected_value() {
+        let whisky_state = test_whisky_state();
+        let config = EffectivePoolConfig::resolve(&whisky_state, &Pool::default());
+        let user_share = whisky_state.jackpot_payout_to_user_bps;
+
+        // Across contribution and pot sizes, expected winnings equal the
+        // contribution less the jackpot edge
+        for (contribution, jackpot) in [(10_000, 100_000_000), (1_000_000, 5_000_000_000), (25_000, 3_000_000_000)] {
+            let odds = calculate_jackpot_probability(contribution, jackpot, user_share, &config);
+            assert!(odds < config.jackpot_max_probability_ubps);
+
+            let expected_value = jackpot_expected_value(odds, jackpot + contribution, user_share);
+            let fair_value = contribution as f64 * (BPS_PER_WHOLE - DEFAULT_JACKPOT_EDGE_BPS) as f64
+                / BPS_PER_WHOLE as f64;
+            assert!((expected_value - fair_value).abs() / fair_value < 0.001);
+        }
+
+        // Doubling the contribution doubles the odds; doubling the pot halves them
+        let odds = calculate_jackpot_probability(10_000, 1_000_000_000, user_share, &config);
+        let doubled = calculate_jackpot_probability(20_000, 999_990_000, user_share, &config);
+        assert!(doubled.abs_diff(odds * 2) <= 1);
+        assert_eq!(calculate_jackpot_probability(10_000, 2_000_010_000, user_share, &config), odds / 2);
+    }
+
+    #[test]
+    fn test_jackpot_probability_bounds() {
+        let whisky_state = test_whisky_state();
+        let config = EffectivePoolConfig::resolve(&whisky_state, &Pool::default());
+        let user_share = whisky_state.jackpot_payout_to_user_bps;
+
+        assert_eq!(calculate_jackpot_probability(0, 1_000_000, user_share, &config), 0);
+        assert_eq!(
+            calculate_jackpot_probability(1_000, 0, user_share, &config),
+            DEFAULT_JACKPOT_BASE_PROBABILITY_UBPS
+        );
+        // Contributions large relative to the pot are capped, paying out less than fair
+        let capped = calculate_jackpot_probability(1_000_000, 1_000_000, user_share, &config);
+        assert_eq!(capped, DEFAULT_JACKPOT_MAX_PROBABILITY_UBPS);
+        assert!(jackpot_expected_value(capped, 2_000_000, user_share) < 1_000_000.0);
+
+        // Per-pool odds replace the global ones
+        let pool = Pool {
+            custom_jackpot_odds: true,
+            custom_jackpot_base_probability_ubps: 0,
+            custom_jackpot_max_probability_ubps: 10_000,
+            custom_jackpot_edge_bps: BPS_PER_WHOLE,
+            ..Default::default()
+        };
+        let custom = EffectivePoolConfig::resolve(&whisky_state, &pool);
+        assert_eq!(calculate_jackpot_probability(1_000, 0, user_share, &custom), 0);
+        assert_eq!(calculate_jackpot_probability(1_000, 1_000_000, user_share, &custom), 0); // 100% edge
+
+        assert!(validate_jackpot_odds(100, 1_000_000, 500).is_ok());
+        assert!(validate_jackpot_odds(1_000_001, 1_000_000, 500).is_err());
+        assert!(validate_jackpot_odds(0, UBPS_PER_WHOLE + 1, 500).is_err());
+        assert!(validate_jackpot_odds(0, 1_000_000, BPS_PER_WHOLE + 1).is_err());
+    }
+
+    #[test]
+    fn test_jackpot_result_frequency() {
+        // 1% odds hit about 1% of the time
+        let hits = (0..100_000u64)
+            .filter(|nonce| calculate_jackpot_result(&get_game_hash("seed", "client", *nonce), 1_000_000))
+            .count();
+        assert!((700..=1_300).contains(&hits), "{} hits", hits);
+
+        let hash = get_game_hash("seed", "client", 0);
+        assert!(!calculate_jackpot_result(&hash, 0));
+        assert!(calculate_jackpot_result(&hash, UBPS_PER_WHOLE));
+    }
+
+    #[test]
+    fn test_jackpot_split() {
+        let default_split = [
+            DEFAULT_JACKPOT_PAYOUT_TO_USER_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_CREATOR_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_POOL_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_WHISKY_BPS,
+        ];
+        assert!(validate_jackpot_split(&default_split).is_ok());
+        assert!(validate_jackpot_split(&[7_000, 1_000, 1_000, 999]).is_err());
+        assert!(validate_jackpot_split(&[u64::MAX, 1, 0, 0]).is_err());
+
+        assert_eq!(calculate_jackpot_split(1_000_000, &default_split).unwrap(), [700_000, 100_000, 100_000, 100_000]);
+
+        // Rounding dust goes to the user and nothing is lost
+        let split = calculate_jackpot_split(1_001, &default_split).unwrap();
+        assert_eq!(split, [701, 100, 100, 100]);
+        assert_eq!(split.iter().sum::<u64>(), 1_001);
+
+        assert!(calculate_jackpot_split(1_000, &[0, 5_000, 5_000, 5_000]).is_err());
+    }
+
+    #[test]
+    fn test_bonus_usage_and_settlement() {
+        // Bonus tokens cover as much of the wager as they can
+        assert_eq!(calculate_bonus_usage(1_000, 0), (0, 1_000));
+        assert_eq!(calculate_bonus_usage(1_000, 400), (400, 600));
+        assert_eq!(calculate_bonus_usage(1_000, 5_000), (1_000, 0));
+
+        // 10% to the jackpot, then the winnings, then the pool
+        assert_eq!(calculate_bonus_settlement(1_000, 0, 1_000).unwrap(), [100, 0, 900]);
+        assert_eq!(calculate_bonus_settlement(1_000, 500, 1_000).unwrap(), [100, 500, 400]);
+        assert_eq!(calculate_bonus_settlement(1_000, 2_000, 1_000).unwrap(), [100, 900, 0]);
+        assert_eq!(calculate_bonus_settlement(0, 2_000, 1_000).unwrap(), [0, 0, 0]);
+
+        // Everything backing the bonus is accounted for
+        let routed = calculate_bonus_settlement(12_345, 7_000, 1_000).unwrap();
+        assert_eq!(routed.iter().sum::<u64>(), 12_345);
+
+        assert!(calculate_bonus_settlement(1_000, 0, BPS_PER_WHOLE * 2).is_err());
+    }
+
+    #[test]
+    fn test_jackpot_win_leaves_lp_liquidity_untouched() {
+        let split = [
+            DEFAULT_JACKPOT_PAYOUT_TO_USER_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_CREATOR_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_POOL_BPS,
+            DEFAULT_JACKPOT_PAYOUT_TO_WHISKY_BPS,
+        ];
+        let wager = 1_000_000;
+        let pool_fee = calculate_fee(wager, DEFAULT_POOL_FEE_BPS);
+        let jackpot_fee = calculate_fee(wager, DEFAULT_MAX_JACKPOT_FEE_BPS);
+        let bet = [1, 1];
+
+        // The vault can hold more than the pool; a win is paid from it in full
+        for (pool_liquidity, jackpot_vault) in [(1_000_000_000u64, 5_000_000u64), (10_000_000, 50_000_000)] {
+            for outcome in 0..bet.len() {
+                // Settlement empties the escrow into the pool and the vault,
+                // then pays the main game from the pool
+                let base_payout = calculate_payout(wager, calculate_multiplier(&bet, outcome)).unwrap();
+                let pool_after_game = pool_liquidity + wager + pool_fee - base_payout;
+                let jackpot_payout = jackpot_vault + jackpot_fee;
+
+                let [to_user, to_creator, to_pool, to_whisky] =
+                    calculate_jackpot_split(jackpot_payout, &split).unwrap();
+                assert_eq!(to_user + to_creator + to_pool + to_whisky, jackpot_payout);
+                assert_eq!(to_pool, calculate_fee(jackpot_payout, DEFAULT_JACKPOT_PAYOUT_TO_POOL_BPS));
+
+                // The pool only gains its share; nothing owed by the jackpot
+                // comes out of LP liquidity
+                let pool_after_jackpot = pool_after_game + to_pool;
+                assert_eq!(pool_after_jackpot - pool_after_game, to_pool);
+                if jackpot_vault > pool_liquidity {
+                    assert!(to_user > pool_liquidity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pool_creation_fee_and_allowlist() {
+        let authority = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let whisky_state = WhiskyState {
+            authority,
+            pool_creation_fee: DEFAULT_POOL_CREATION_FEE,
+            pool_creation_allowed: true,
+            ..test_whisky_state()
+        };
+        assert_eq!(calculate_pool_creation_fee(&whisky_state, &creator), DEFAULT_POOL_CREATION_FEE);
+        assert_eq!(calculate_pool_creation_fee(&whisky_state, &authority), 0);
+
+        // Open creation ignores the allowlist
+        assert!(validate_pool_creation(&whisky_state, &creator, false).is_ok());
+
+        let restricted = WhiskyState { pool_creation_restricted: true, ..whisky_state };
+        assert!(validate_pool_creation(&restricted, &creator, false).is_err());
+        assert!(validate_pool_creation(&restricted, &creator, true).is_ok());
+        assert!(validate_pool_creation(&restricted, &authority, false).is_ok());
+
+        let closed = WhiskyState { pool_creation_allowed: false, ..restricted };
+        assert!(validate_pool_creation(&closed, &authority, true).is_err());
+    }
+
+    #[test]
+    fn test_validate_pool_config() {
+        let whisky_state = test_whisky_state();
+        let pool = Pool { min_wager: DEFAULT_POOL_MIN_WAGER, ..Default::default() };
+        assert!(validate_pool_config(&whisky_state, &pool).is_ok());
+
+        let invalid = [
+            Pool { min_wager: MIN_WAGER - 1, ..pool.clone() },
+            Pool { deposit_limit: true, deposit_limit_amount: 0, ..pool.clone() },
+            Pool { deposit_whitelist_required: true, ..pool.clone() },
+            Pool { custom_max_payout: true, custom_max_payout_bps: 0, ..pool.clone() },
+            Pool { custom_max_payout: true, custom_max_payout_bps: DEFAULT_MAX_PAYOUT_BPS + 1, ..pool.clone() },
+            Pool { custom_max_creator_fee: true, custom_max_creator_fee_bps: DEFAULT_MAX_CREATOR_FEE_BPS + 1, ..pool.clone() },
+            Pool { custom_max_jackpot_fee: true, custom_max_jackpot_fee_bps: DEFAULT_MAX_JACKPOT_FEE_BPS + 1, ..pool.clone() },
+            Pool { custom_pool_fee: true, custom_pool_fee_bps: BPS_PER_WHOLE, ..pool.clone() },
+            Pool { custom_whisky_fee: true, custom_whisky_fee_bps: u64::MAX, ..pool.clone() },
+            Pool { custom_pool_withdraw_fee: true, custom_pool_withdraw_fee_bps: DEFAULT_POOL_WITHDRAW_FEE_BPS + 1, ..pool.clone() },
+            Pool { withdraw_limit: true, withdraw_limit_bps: 0, withdraw_limit_window_seconds: 3_600, ..pool.clone() },
+            Pool { withdraw_limit: true, withdraw_limit_bps: MIN_WITHDRAW_LIMIT_BPS - 1, withdraw_limit_window_seconds: 3_600, ..pool.clone() },
+            Pool { withdraw_limit: true, withdraw_limit_bps: 1_000, withdraw_limit_window_seconds: 0, ..pool.clone() },
+            Pool {
+                withdraw_limit: true,
+                withdraw_limit_bps: 1_000,
+                withdraw_limit_window_seconds: MAX_WITHDRAW_LIMIT_WINDOW_SECONDS + 1,
+                ..pool.clone()
+            },
+            Pool { withdraw_delay_seconds: -1, ..pool.clone() },
+            Pool { withdraw_delay_seconds: MAX_WITHDRAW_DELAY_SECONDS + 1, ..pool.clone() },
+            Pool { custom_jackpot_odds: true, custom_jackpot_max_probability_ubps: DEFAULT_JACKPOT_MAX_PROBABILITY_UBPS + 1, ..pool.clone() },
+            Pool { custom_jackpot_odds: true, custom_jackpot_base_probability_ubps: 1, custom_jackpot_max_probability_ubps: 0, ..pool.clone() },
+        ];
+        for pool in invalid.iter() {
+            assert!(validate_pool_config(&whisky_state, pool).is_err());
+        }
+
+        // The limit bounds themselves are accepted
+        let limited = Pool {
+            withdraw_limit: true,
+            withdraw_limit_bps: MIN_WITHDRAW_LIMIT_BPS,
+            withdraw_limit_window_seconds: MAX_WITHDRAW_LIMIT_WINDOW_SECONDS,
+            ..pool.clone()
+        };
+        assert!(validate_pool_config(&whisky_state, &limited).is_ok());
+
+        // Flags off means the stored values are ignored
+        let pool = Pool { custom_max_payout_bps: 0, custom_pool_fee_bps: BPS_PER_WHOLE, ..pool };
+        assert!(validate_pool_config(&whisky_state, &pool).is_ok());
+    }
+
+    #[test]
+    fn test_validate_deposit_limit() {
+        let pool = Pool { deposit_limit: true, deposit_limit_amount: 10_000, ..Default::default() };
+        assert!(validate_deposit_limit(&pool, 6_000, 4_000).is_ok()); // Exactly at the cap
+        assert!(validate_deposit_limit(&pool, 6_000, 4_001).is_err());
+        assert!(validate_deposit_limit(&pool, 12_000, 1).is_err()); // Already over the cap
+
+        let uncapped = Pool { deposit_limit: false, ..pool };
+        assert!(validate_deposit_limit(&uncapped, u64::MAX, 1).is_ok());
+    }
+
+    #[test]
+    fn test_calculate_withdrawal() {
+        // 100 of 1,000 LP tokens against 50,000 liquidity with 10,000 reserved
+        assert_eq!(calculate_withdrawal(100, 50_000, 10_000, 1_000, 0), (4_000, 0));
+        assert_eq!(calculate_withdrawal(100, 50_000, 10_000, 1_000, 50), (3_980, 20));
+        assert_eq!(calculate_withdrawal(100, 50_000, 0, 0, 50), (0, 0));
+    }
+
+    #[test]
+    fn test_record_withdrawal() {
+        let mut pool = Pool {
+            withdraw_limit: true,
+            withdraw_limit_bps: 2_000, // 20% per window
+            withdraw_limit_window_seconds: 3_600,
+            ..Default::default()
+        };
+
+        // Window opens at t0 with 10,000 liquidity: cap is 2,000
+        let t0 = 1_700_000_000;
+        assert!(record_withdrawal(&mut pool, 10_000, 1_500, t0).is_ok());
+        assert!(record_withdrawal(&mut pool, 8_500, 500, t0 + 100).is_ok()); // Exactly at the cap
+        assert!(record_withdrawal(&mut pool, 8_000, 1, t0 + 3_599).is_err());
+        assert_eq!(pool.withdraw_window_withdrawn, 2_000);
+
+        // A new window snapshots the reduced liquidity
+        assert!(record_withdrawal(&mut pool, 8_000, 1_600, t0 + 3_600).is_ok());
+        assert!(record_withdrawal(&mut pool, 6_400, 1, t0 + 3_601).is_err());
+
+        let uncapped = &mut Pool { withdraw_limit: false, ..pool };
+        assert!(record_withdrawal(uncapped, 1, u64::MAX, 0).is_ok());
+    }
+
+    #[test]
+    fn test_calculate_unlock_timestamp() {
+        let now = 1_700_000_000;
+        assert_eq!(calculate_unlock_timestamp(now, 0).unwrap(), now);
+        assert_eq!(calculate_unlock_timestamp(now, 86_400).unwrap(), now + 86_400);
+
+        // Exit stays reachable whatever delay the pool holds
+        assert_eq!(calculate_unlock_timestamp(now, i64::MAX).unwrap(), now + MAX_WITHDRAW_DELAY_SECONDS);
+        assert_eq!(calculate_unlock_timestamp(now, -1).unwrap(), now);
+    }
+
+    #[test]
+    fn test_validate_deposit_whitelist() {
+        let user = Pubkey::new_unique();
+        let whitelist_mint = Pubkey::new_unique();
+        let pool = Pool {
+            deposit_whitelist_required: true,
+            deposit_whitelist_address: whitelist_mint,
+            ..Default::default()
+        };
+        let holding = spl_token_2022::state::Account {
+            mint: whitelist_mint,
+            owner: user,
+            amount: 1,
+            ..Default::default()
+        };
+
+        assert!(validate_deposit_whitelist(&pool, &user, Some(&holding)).is_ok());
+        assert!(validate_deposit_whitelist(&pool, &user, None).is_err());
+        assert!(validate_deposit_whitelist(&pool, &Pubkey::new_unique(), Some(&holding)).is_err());
+
+        let wrong_mint = spl_token_2022::state::Account { mint: Pubkey::new_unique(), ..holding };
+        assert!(validate_deposit_whitelist(&pool, &user, Some(&wrong_mint)).is_err());
+
+        let empty = spl_token_2022::state::Account { amount: 0, ..holding };
+        assert!(validate_deposit_whitelist(&pool, &user, Some(&empty)).is_err());
+
+        let open_pool = Pool { deposit_whitelist_required: false, ..pool };
+        assert!(validate_deposit_whitelist(&open_pool, &user, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_mint_extensions() {
+        assert!(validate_mint_extensions(&[]).is_ok());
+        assert!(validate_mint_extensions(&[ExtensionType::TransferFeeConfig, ExtensionType::MetadataPointer]).is_ok());
+        for extension in UNSUPPORTED_MINT_EXTENSIONS {
+            assert!(validate_mint_extensions(&[ExtensionType::TransferFeeConfig, extension]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_transfer_fee_amounts() {
+        // 1% fee capped at 500
+        let transfer_fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: 500.into(),
+            transfer_fee_basis_points: 100.into(),
+        };
+        assert_eq!(calculate_net_transfer_amount(Some(&transfer_fee), 10_000).unwrap(), 9_900);
+        assert_eq!(calculate_net_transfer_amount(Some(&transfer_fee), 1_000_000).unwrap(), 999_500);
+
+        // Grossing up delivers exactly the requested amount
+        for net in [1, 9_900, 10_000, 49_999, 1_000_000] {
+            let gross = calculate_gross_transfer_amount(Some(&transfer_fee), net).unwrap();
+            assert_eq!(calculate_net_transfer_amount(Some(&transfer_fee), gross).unwrap(), net);
+        }
+
+        // Mints without the extension transfer the full amount
+        assert_eq!(calculate_gross_transfer_amount(None, 10_000).unwrap(), 10_000);
+        assert_eq!(calculate_net_transfer_amount(None, 10_000).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn test_validate_native_sol() {
+        let usdc = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        assert!(validate_native_sol(true, &spl_token::native_mint::ID).is_ok());
+        assert!(validate_native_sol(true, &spl_token_2022::native_mint::ID).is_ok());
+        assert!(validate_native_sol(true, &usdc).is_err());
+        assert!(validate_native_sol(false, &usdc).is_ok());
+    }
+
+    #[test]
+    fn test_lp_token_metadata() {
+        assert_eq!(lp_token_name("USDC"), "Whisky USDC LP");
+        assert_eq!(lp_token_symbol("USDC"), "USDC-LP");
+
+        // Long symbols are cut to fit the metadata program limits
+        let long_symbol = "VERYLONGTOKENSYMBOLTHATNEVERENDS";
+        assert!(lp_token_name(long_symbol).len() <= mpl_token_metadata::MAX_NAME_LENGTH);
+        assert_eq!(lp_token_symbol(long_symbol), "VERYLON-LP");
+
+        // Null-padded metadata symbols are trimmed, missing ones fall back to the mint
+        let mint = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        assert_eq!(underlying_display_symbol(Some("USDC\0\0\0\0\0\0"), &mint), "USDC");
+        assert_eq!(underlying_display_symbol(Some("\0\0\0"), &mint), "EPjF");
+        assert_eq!(underlying_display_symbol(None, &mint), "EPjF");
+    }
+
+    #[test]
+    fn test_calculate_max_payout() {
+        assert_eq!(calculate_max_payout(&[90, 10], 1000).unwrap(), 10_000); // 10x on the 10% outcome
+        assert_eq!(calculate_max_payout(&[50, 50], 1000).unwrap(), 2_000);
+        assert_eq!(calculate_max_payout(&[0, 100], 1000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_reserved_liquidity_blocks_front_running() {
+        // 1,000 LP tokens backed by 100,000 tokens, with a pending game that
+        // can pay out up to 40,000
+        let lp_supply = 1_000;
+        let pool_liquidity = 100_000;
+        let reserved = calculate_max_payout(&[3, 1], 10_000).unwrap(); // 4x
+        assert_eq!(reserved, 40_000);
+
+        // An LP exiting before settlement is priced as if the game already won
+        let available = calculate_available_liquidity(pool_liquidity, reserved);
+        assert_eq!(calculate_withdraw_amount(500, available, lp_supply), 30_000);
+        assert_eq!(calculate_available_liquidity(10, 20), 0);
+
+        // New games are sized against the unreserved liquidity only
+        assert!(validate_max_payout(&[1, 1], 30_000, available, BPS_PER_WHOLE).is_ok());
+        assert!(validate_max_payout(&[1, 1], 30_001, available, BPS_PER_WHOLE).is_err());
+    }
+
+    #[test]
+    #[allow(clippy::useless_vec)]
     fn test_validate_bet() {
         assert!(validate_bet(&vec![25, 25, 25, 25]).is_ok());
         assert!(validate_bet(&vec![0, 0]).is_err()); // No valid outcomes
@@ -0,0 +1,149 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { createMint, getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { WhiskyCore } from "../../target/types/whisky_core";
+
+const METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/** Reads name, symbol and uri from a Metaplex metadata account */
+function decodeMetadata(data: Buffer): { name: string; symbol: string; uri: string } {
+  // key (1) + update_authority (32) + mint (32)
+  let offset = 1 + 32 + 32;
+  const readString = () => {
+    const length = data.readUInt32LE(offset);
+    offset += 4;
+    const value = data.subarray(offset, offset + length).toString("utf8");
+    offset += length;
+    return value.replace(/\0/g, "").trim();
+  };
+  return { name: readString(), symbol: readString(), uri: readString() };
+}
+
+describe("pool_set_lp_metadata", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.WhiskyCore as Program<WhiskyCore>;
+  const authority = provider.wallet.publicKey;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const [whiskyState] = PublicKey.findProgramAddressSync(
+    [Buffer.from("WHISKY_STATE")],
+    program.programId
+  );
+
+  let underlyingTokenMint: PublicKey;
+  let pool: PublicKey;
+  let lpMint: PublicKey;
+  let lpMetadata: PublicKey;
+
+  before(async () => {
+    if (!(await provider.connection.getAccountInfo(whiskyState))) {
+      await program.methods
+        .whiskyInitialize()
+        .accountsPartial({ whiskyState, initializer: authority })
+        .rpc();
+    }
+
+    underlyingTokenMint = await createMint(provider.connection, payer, authority, null, 6);
+    const poolAuthority = Keypair.generate().publicKey;
+
+    [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("POOL"), underlyingTokenMint.toBuffer(), poolAuthority.toBuffer()],
+      program.programId
+    );
+    [lpMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("POOL_LP_MINT"), underlyingTokenMint.toBuffer(), poolAuthority.toBuffer()],
+      program.programId
+    );
+    const [poolJackpotTokenAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("POOL_JACKPOT"), pool.toBuffer()],
+      program.programId
+    );
+    [lpMetadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), METADATA_PROGRAM_ID.toBuffer(), lpMint.toBuffer()],
+      METADATA_PROGRAM_ID
+    );
+
+    await program.methods
+      .poolInitialize(poolAuthority, PublicKey.default)
+      .accountsPartial({
+        whiskyState,
+        pool,
+        underlyingTokenMint,
+        poolAuthority,
+        lpMint,
+        poolUnderlyingTokenAccount: getAssociatedTokenAddressSync(underlyingTokenMint, pool, true),
+        poolJackpotTokenAccount,
+        approvedMint: null,
+        distributionRecipient: authority,
+        user: authority,
+      })
+      .rpc();
+  });
+
+  const setLpMetadata = (uri: string) =>
+    program.methods
+      .poolSetLpMetadata(uri)
+      .accountsPartial({
+        whiskyState,
+        pool,
+        underlyingTokenMint,
+        underlyingMetadata: null,
+        lpMint,
+        lpMetadata,
+        authority,
+        metadataProgram: METADATA_PROGRAM_ID,
+      })
+      .rpc();
+
+  it("creates LP metadata named after the underlying mint", async () => {
+    await setLpMetadata("https://whisky.game/lp.json");
+
+    const account = await provider.connection.getAccountInfo(lpMetadata);
+    expect(account?.owner.toBase58()).to.equal(METADATA_PROGRAM_ID.toBase58());
+
+    // Without underlying metadata the mint address prefix stands in for the symbol
+    const symbol = underlyingTokenMint.toBase58().slice(0, 4);
+    const metadata = decodeMetadata(account!.data);
+    expect(metadata.name).to.equal(`Whisky ${symbol} LP`);
+    expect(metadata.symbol).to.equal(`${symbol}-LP`);
+    expect(metadata.uri).to.equal("https://whisky.game/lp.json");
+  });
+
+  it("updates existing LP metadata", async () => {
+    await setLpMetadata("https://whisky.game/lp-v2.json");
+
+    const account = await provider.connection.getAccountInfo(lpMetadata);
+    expect(decodeMetadata(account!.data).uri).to.equal("https://whisky.game/lp-v2.json");
+  });
+
+  it("rejects signers other than the pool or protocol authority", async () => {
+    const outsider = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(outsider.publicKey, 1_000_000_000)
+    );
+
+    try {
+      await program.methods
+        .poolSetLpMetadata("https://example.com/lp.json")
+        .accountsPartial({
+          whiskyState,
+          pool,
+          underlyingTokenMint,
+          underlyingMetadata: null,
+          lpMint,
+          lpMetadata,
+          authority: outsider.publicKey,
+          metadataProgram: METADATA_PROGRAM_ID,
+        })
+        .signers([outsider])
+        .rpc();
+      expect.fail("expected pool_set_lp_metadata to fail");
+    } catch (err) {
+      expect((err as anchor.AnchorError).error.errorCode.code).to.equal("Unauthorized");
+    }
+  });
+});